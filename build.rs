@@ -19,51 +19,65 @@ fn main() {
 
     config.file("src/c/common.c").compile("common");
 
-    let mut config = shared_config.clone();
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        let mut config = shared_config.clone();
 
-    #[cfg(not(target_env = "msvc"))]
-    config.flag("-msse2");
+        #[cfg(not(target_env = "msvc"))]
+        config.flag("-msse2");
 
-    config
-        .file("src/c/mshabal_128_sse2.c")
-        .file("src/c/noncegen_128_sse2.c")
-        .compile("shabal_sse2");
+        config
+            .file("src/c/mshabal_128_sse2.c")
+            .file("src/c/noncegen_128_sse2.c")
+            .compile("shabal_sse2");
 
-    let mut config = shared_config.clone();
+        let mut config = shared_config.clone();
 
-    #[cfg(target_env = "msvc")]
-    config.flag("/arch:AVX");
+        #[cfg(target_env = "msvc")]
+        config.flag("/arch:AVX");
 
-    #[cfg(not(target_env = "msvc"))]
-    config.flag("-mavx");
+        #[cfg(not(target_env = "msvc"))]
+        config.flag("-mavx");
 
-    config
-        .file("src/c/mshabal_128_avx.c")
-        .file("src/c/noncegen_128_avx.c")
-        .compile("shabal_avx");
+        config
+            .file("src/c/mshabal_128_avx.c")
+            .file("src/c/noncegen_128_avx.c")
+            .compile("shabal_avx");
 
-    let mut config = shared_config.clone();
+        let mut config = shared_config.clone();
 
-    #[cfg(target_env = "msvc")]
-    config.flag("/arch:AVX2");
+        #[cfg(target_env = "msvc")]
+        config.flag("/arch:AVX2");
 
-    #[cfg(not(target_env = "msvc"))]
-    config.flag("-mavx2");
+        #[cfg(not(target_env = "msvc"))]
+        config.flag("-mavx2");
 
-    config
-        .file("src/c/mshabal_256_avx2.c")
-        .file("src/c/noncegen_256_avx2.c")
-        .compile("shabal_avx2");
-    let mut config = shared_config.clone();
+        config
+            .file("src/c/mshabal_256_avx2.c")
+            .file("src/c/noncegen_256_avx2.c")
+            .compile("shabal_avx2");
 
-    #[cfg(target_env = "msvc")]
-    config.flag("/arch:AVX512");
+        let mut config = shared_config.clone();
 
-    #[cfg(not(target_env = "msvc"))]
-    config.flag("-mavx512f");
+        #[cfg(target_env = "msvc")]
+        config.flag("/arch:AVX512");
+
+        #[cfg(not(target_env = "msvc"))]
+        config.flag("-mavx512f");
+
+        config
+            .file("src/c/mshabal_512_avx512f.c")
+            .file("src/c/noncegen_512_avx512f.c")
+            .compile("shabal_avx512");
+    }
 
-    config
-        .file("src/c/mshabal_512_avx512f.c")
-        .file("src/c/noncegen_512_avx512f.c")
-        .compile("shabal_avx512");
+    #[cfg(target_arch = "aarch64")]
+    {
+        let mut config = shared_config.clone();
+        config.flag("-march=armv8-a+simd");
+        config
+            .file("src/c/mshabal_128_neon.c")
+            .file("src/c/noncegen_128_neon.c")
+            .compile("shabal_neon");
+    }
 }
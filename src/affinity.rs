@@ -0,0 +1,84 @@
+// Core topology detection and thread pinning for heterogeneous (big.LITTLE
+// / Ampere-style) CPUs, where performance and efficiency cores have very
+// different hashing throughput and should not be scheduled identically.
+use libc::{cpu_set_t, sched_setaffinity, CPU_SET, CPU_ZERO};
+use std::fs;
+use std::mem;
+
+#[derive(Debug, Clone, Copy)]
+pub struct CoreInfo {
+    pub id: usize,
+    /// relative hashing capacity of this core, derived from its max clock
+    /// frequency; cores in the same cluster share the same capacity.
+    pub capacity: u64,
+}
+
+/// One cluster of cores sharing the same capacity, ordered from the
+/// highest-capacity (performance) cluster to the lowest (efficiency).
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    pub capacity: u64,
+    pub core_ids: Vec<usize>,
+}
+
+/// reads per-core max frequency from sysfs to find clusters of
+/// differently-capable cores; falls back to a single uniform cluster of all
+/// cores if the topology can't be determined (e.g. non-Linux).
+pub fn detect_topology() -> Vec<Cluster> {
+    let num_cores = num_cpus::get();
+    let mut cores = Vec::with_capacity(num_cores);
+
+    for id in 0..num_cores {
+        let path = format!("/sys/devices/system/cpu/cpu{}/cpufreq/cpuinfo_max_freq", id);
+        let capacity = fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(1);
+        cores.push(CoreInfo { id, capacity });
+    }
+
+    let mut capacities: Vec<u64> = cores.iter().map(|c| c.capacity).collect();
+    capacities.sort_unstable();
+    capacities.dedup();
+
+    let mut clusters: Vec<Cluster> = capacities
+        .into_iter()
+        .map(|capacity| Cluster {
+            capacity,
+            core_ids: cores.iter().filter(|c| c.capacity == capacity).map(|c| c.id).collect(),
+        })
+        .collect();
+    clusters.sort_by(|a, b| b.capacity.cmp(&a.capacity));
+    clusters
+}
+
+/// pins the calling thread to a single physical core. Returns false (and
+/// leaves affinity untouched) on platforms without sched_setaffinity.
+#[cfg(target_os = "linux")]
+pub fn pin_current_thread(core_id: usize) -> bool {
+    unsafe {
+        let mut set: cpu_set_t = mem::zeroed();
+        CPU_ZERO(&mut set);
+        CPU_SET(core_id, &mut set);
+        sched_setaffinity(0, mem::size_of::<cpu_set_t>(), &set) == 0
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin_current_thread(_core_id: usize) -> bool {
+    false
+}
+
+/// assigns worker indices `0..total_threads` to physical core ids, biased
+/// toward the highest-capacity cluster first so, combined with the
+/// scheduler's throughput-based chunk sizing, fast cores get work sooner.
+/// Each entry is `(core_id, capacity)`, letting the scheduler weight a
+/// worker's initial chunk size by its core's capacity before any EWMA rate
+/// has been measured.
+pub fn place_workers(clusters: &[Cluster], total_threads: usize) -> Vec<(usize, u64)> {
+    let all_cores: Vec<(usize, u64)> = clusters
+        .iter()
+        .flat_map(|c| c.core_ids.iter().map(move |&id| (id, c.capacity)))
+        .collect();
+    all_cores.into_iter().cycle().take(total_threads).collect()
+}
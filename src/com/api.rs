@@ -0,0 +1,114 @@
+use std::u64;
+
+/// Burst mining-info polling request, `requestType=getMiningInfo`.
+#[derive(Debug, Serialize)]
+pub struct GetMiningInfoRequest<'a> {
+    #[serde(rename = "requestType")]
+    pub request_type: &'a str,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MiningInfoResponse {
+    pub height: u64,
+    #[serde(rename = "baseTarget")]
+    pub base_target: u64,
+    #[serde(rename = "generationSignature")]
+    pub generation_signature: String,
+    #[serde(rename = "targetDeadline", default = "default_target_deadline")]
+    pub target_deadline: u64,
+}
+
+fn default_target_deadline() -> u64 {
+    u64::MAX
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubmitNonceResponse {
+    #[serde(default)]
+    pub result: String,
+    #[serde(default)]
+    pub deadline: u64,
+}
+
+/// The pool's error payload, `{"errorCode": ..., "errorDescription": ...}`.
+#[derive(Debug, Clone, Deserialize)]
+struct PoolErrorResponse {
+    #[serde(rename = "errorCode")]
+    error_code: i32,
+    #[serde(rename = "errorDescription", default)]
+    error_description: String,
+}
+
+/// A pool-reported protocol error: it understood the request but rejected
+/// it, carrying whatever code/message it sent back.
+#[derive(Debug, Clone)]
+pub struct PoolError {
+    pub code: i32,
+    pub message: String,
+}
+
+/// Why a request failed, distinguished so callers know whether resending
+/// is worth it.
+#[derive(Debug)]
+pub enum TransientError {
+    /// never got a response at all: connection reset, connect/read timeout, ...
+    Http(reqwest::Error),
+    /// got a response, but the server is overloaded (HTTP 5xx).
+    ServerError(u16),
+    /// got a response with nothing in it.
+    EmptyBody,
+    /// the pool itself reported it's too busy to accept the submission right now.
+    PoolBusy(PoolError),
+}
+
+#[derive(Debug)]
+pub enum FetchError {
+    /// worth resending: the failure is likely to clear up on its own.
+    Transient(TransientError),
+    /// the pool understood the request and rejected it for a protocol
+    /// reason (stale block, deadline above its limit, bad account, ...);
+    /// resending the exact same submission won't help.
+    Permanent(PoolError),
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> Self {
+        FetchError::Transient(TransientError::Http(e))
+    }
+}
+
+fn is_pool_busy(error: &PoolErrorResponse) -> bool {
+    error.error_description.is_empty() || error.error_description == "limit exceeded"
+}
+
+/// Parses a pool response body, distinguishing a `{"errorCode": ...}`
+/// protocol error (further split into `Transient`/`Permanent` depending on
+/// whether the pool is just busy) from a successful payload.
+pub fn parse_json_result<T>(body: &[u8]) -> Result<T, FetchError>
+where
+    T: serde::de::DeserializeOwned,
+{
+    if body.is_empty() {
+        return Err(FetchError::Transient(TransientError::EmptyBody));
+    }
+
+    if let Ok(wire_error) = serde_json::from_slice::<PoolErrorResponse>(body) {
+        let busy = is_pool_busy(&wire_error);
+        let pool_error = PoolError {
+            code: wire_error.error_code,
+            message: wire_error.error_description,
+        };
+        return if busy {
+            Err(FetchError::Transient(TransientError::PoolBusy(pool_error)))
+        } else {
+            Err(FetchError::Permanent(pool_error))
+        };
+    }
+
+    serde_json::from_slice::<T>(body).map_err(|e| {
+        FetchError::Permanent(PoolError {
+            code: -1,
+            message: format!("can't parse pool response: {}", e),
+        })
+    })
+}
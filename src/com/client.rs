@@ -1,22 +1,52 @@
 use crate::com::api::*;
+use futures::future::{self, Either};
 use futures::stream::Stream;
 use futures::Future;
 use reqwest::header::{HeaderMap, HeaderName};
-use reqwest::r#async::{Client as InnerClient, ClientBuilder, Decoder};
+use reqwest::r#async::{Chunk, Client as InnerClient, ClientBuilder, Decoder};
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio::runtime::TaskExecutor;
+use tokio::timer::Delay;
 use url::form_urlencoded::byte_serialize;
 use url::Url;
 
-/// A client for communicating with Pool/Proxy/Wallet.
+/// after this many consecutive failed requests against an endpoint, it's
+/// marked unhealthy and the client fails over to the next one in priority
+/// order.
+const MAX_CONSECUTIVE_FAILURES: usize = 3;
+
+/// a pool/proxy endpoint and its observed health, as tracked by `Client`.
+#[derive(Debug)]
+struct Endpoint {
+    url: Url,
+    healthy: AtomicBool,
+    consecutive_failures: AtomicUsize,
+}
+
+impl Endpoint {
+    fn new(url: Url) -> Self {
+        Self {
+            url,
+            healthy: AtomicBool::new(true),
+            consecutive_failures: AtomicUsize::new(0),
+        }
+    }
+}
+
+/// A client for communicating with Pool/Proxy/Wallet. Holds a
+/// priority-ordered list of endpoints and fails over between them based on
+/// observed health - see `record_result` and `start_health_probe`.
 #[derive(Clone, Debug)]
 pub struct Client {
     inner: InnerClient,
     secret_phrase: Arc<String>,
-    base_uri: Url,
+    endpoints: Arc<Vec<Endpoint>>,
+    active: Arc<AtomicUsize>,
     headers: Arc<HeaderMap>,
 }
 
@@ -110,32 +140,134 @@ impl Client {
         headers
     }
 
-    /// Create a new client communicating with Pool/Proxy/Wallet.
+    /// Create a new client communicating with Pool/Proxy/Wallet. `urls` is
+    /// a priority-ordered list of endpoints; the first is used until it's
+    /// marked unhealthy.
     pub fn new(
-        base_uri: Url,
+        urls: Vec<Url>,
         secret_phrase: String,
-        timeout: u64,
+        connect_timeout: u64,
+        request_timeout: u64,
         proxy_details: ProxyDetails,
         additional_headers: Arc<HashMap<String, String>>,
     ) -> Self {
+        assert!(!urls.is_empty(), "need at least one pool/proxy url");
         let secret_phrase_encoded = byte_serialize(secret_phrase.as_bytes()).collect();
 
         let headers =
             Client::submit_nonce_headers(proxy_details, additional_headers);
 
         let client = ClientBuilder::new()
-            .timeout(Duration::from_millis(timeout))
+            .connect_timeout(Duration::from_millis(connect_timeout))
+            .timeout(Duration::from_millis(request_timeout))
             .build()
             .unwrap();
 
         Self {
             inner: client,
             secret_phrase: Arc::new(secret_phrase_encoded),
-            base_uri,
+            endpoints: Arc::new(urls.into_iter().map(Endpoint::new).collect()),
+            active: Arc::new(AtomicUsize::new(0)),
             headers: Arc::new(headers),
         }
     }
 
+    /// the endpoint currently in use.
+    pub fn current_endpoint(&self) -> Url {
+        self.endpoints[self.active.load(AtomicOrdering::SeqCst)].url.clone()
+    }
+
+    /// every configured endpoint, in priority order, with its last observed
+    /// health - so operators can see which ones have been failed away from
+    /// rather than just which one is currently active.
+    pub fn endpoint_health(&self) -> Vec<(Url, bool)> {
+        self.endpoints
+            .iter()
+            .map(|e| (e.url.clone(), e.healthy.load(AtomicOrdering::SeqCst)))
+            .collect()
+    }
+
+    /// records the outcome of a request against the endpoint it was sent
+    /// to, failing over to the next one in priority order once it's failed
+    /// `MAX_CONSECUTIVE_FAILURES` times in a row. Protocol rejections
+    /// (`FetchError::Permanent`) say nothing about the endpoint's health,
+    /// so they don't count towards this. `idx` must be the endpoint that
+    /// was active when the request was sent, not whatever is active now -
+    /// a failover/failback can flip `self.active` while the request is
+    /// still in flight, and attributing the outcome to the wrong endpoint
+    /// would corrupt its failure count.
+    fn record_result<T>(&self, idx: usize, result: &Result<T, FetchError>) {
+        let endpoint = &self.endpoints[idx];
+        match result {
+            Ok(_) => {
+                endpoint.consecutive_failures.store(0, AtomicOrdering::SeqCst);
+            }
+            Err(FetchError::Permanent(_)) => {}
+            Err(FetchError::Transient(_)) => {
+                let failures = endpoint.consecutive_failures.fetch_add(1, AtomicOrdering::SeqCst) + 1;
+                if failures >= MAX_CONSECUTIVE_FAILURES {
+                    endpoint.healthy.store(false, AtomicOrdering::SeqCst);
+                    self.failover_from(idx);
+                }
+            }
+        }
+    }
+
+    /// advances `active` to the next endpoint in priority order, if any.
+    /// Uses `fetch_max` rather than an unconditional store: `idx` is the
+    /// endpoint a stale, just-completed request was sent to, which can be
+    /// behind whatever `active` has already moved on to by the time this
+    /// runs - an unconditional store would walk `active` back to a
+    /// known-bad endpoint and undo that later, correct failover.
+    fn failover_from(&self, idx: usize) {
+        if idx + 1 < self.endpoints.len() {
+            let prev = self.active.fetch_max(idx + 1, AtomicOrdering::SeqCst);
+            if prev >= idx + 1 {
+                return;
+            }
+            warn!(
+                "endpoint unhealthy after {} consecutive failures: {} -> switching to {}",
+                MAX_CONSECUTIVE_FAILURES,
+                self.endpoints[idx].url,
+                self.endpoints[idx + 1].url
+            );
+        } else {
+            warn!(
+                "endpoint unhealthy after {} consecutive failures: {} (no lower-priority endpoint left)",
+                MAX_CONSECUTIVE_FAILURES,
+                self.endpoints[idx].url
+            );
+        }
+    }
+
+    /// issues a lightweight `get_mining_info` against `endpoints[idx]`,
+    /// irrespective of which endpoint is currently active; used by the
+    /// background health probe to check on higher-priority endpoints.
+    fn probe(&self, idx: usize) -> impl Future<Item = (), Error = ()> {
+        let mut uri = self.endpoints[idx].url.clone();
+        uri.path_segments_mut()
+            .map_err(|_| "cannot be base")
+            .unwrap()
+            .pop_if_empty()
+            .push("burst");
+        self.inner
+            .get(uri)
+            .headers((*self.headers).clone())
+            .query(&GetMiningInfoRequest {
+                request_type: &"getMiningInfo",
+            })
+            .send()
+            .map(|_| ())
+            .map_err(|_| ())
+    }
+
+    /// spawns a background loop that, every `interval_ms`, re-checks
+    /// endpoints preferred over the currently active one and fails back to
+    /// the most-preferred one that responds.
+    pub fn start_health_probe(&self, executor: TaskExecutor, interval_ms: u64) {
+        probe_loop(self.clone(), executor, interval_ms);
+    }
+
     /// Get current mining info.
     pub fn get_mining_info(&self, capacity: u64, additional_headers: Arc<HashMap<String, String>>, xpu_string : Arc<String>) -> impl Future<Item = MiningInfoResponse, Error = FetchError> {
         let mut headers = (*self.headers).clone();
@@ -151,26 +283,71 @@ impl Client {
             let header_name = HeaderName::from_bytes(&key.clone().into_bytes()).unwrap();
             headers.insert(header_name, value.parse().unwrap());
         }
+        let client = self.clone();
+        let idx = self.active.load(AtomicOrdering::SeqCst);
         self.inner
             .get(self.uri_for("burst"))
-            .headers(headers)          
+            .headers(headers)
             .query(&GetMiningInfoRequest {
                 request_type: &"getMiningInfo",
             })
             .send()
+            .from_err::<FetchError>()
             .and_then(|mut res| {
+                if res.status().is_server_error() {
+                    return Either::A(future::err(FetchError::Transient(
+                        TransientError::ServerError(res.status().as_u16()),
+                    )));
+                }
                 let body = mem::replace(res.body_mut(), Decoder::empty());
-                body.concat2()
+                Either::B(
+                    body.concat2()
+                        .from_err::<FetchError>()
+                        .and_then(|body| parse_json_result(&body)),
+                )
             })
+            .then(move |res| {
+                client.record_result(idx, &res);
+                res
+            })
+    }
+
+    /// opens a long-lived connection to the pool's job-push endpoint and
+    /// resolves to the raw byte stream of the response body - the push
+    /// counterpart to polling `get_mining_info`. HTTP chunking has no
+    /// relation to application-message boundaries (a notification can
+    /// arrive split across chunks, or several can coalesce into one), so
+    /// framing the individual notifications out of this byte stream is left
+    /// to the caller (`push::NdjsonStream`) rather than assumed here.
+    pub fn open_mining_info_stream(
+        &self,
+    ) -> impl Future<Item = Box<dyn Stream<Item = Chunk, Error = FetchError> + Send>, Error = FetchError> {
+        let client = self.clone();
+        let idx = self.active.load(AtomicOrdering::SeqCst);
+        self.inner
+            .get(self.uri_for("burst/notify"))
+            .headers((*self.headers).clone())
+            .send()
             .from_err::<FetchError>()
-            .and_then(|body| match parse_json_result(&body) {
-                Ok(x) => Ok(x),
-                Err(e) => Err(e.into()),
+            .and_then(|mut res| {
+                if res.status().is_server_error() {
+                    return Either::A(future::err(FetchError::Transient(
+                        TransientError::ServerError(res.status().as_u16()),
+                    )));
+                }
+                let body = mem::replace(res.body_mut(), Decoder::empty());
+                let stream: Box<dyn Stream<Item = Chunk, Error = FetchError> + Send> =
+                    Box::new(body.from_err::<FetchError>());
+                Either::B(future::ok(stream))
+            })
+            .then(move |res| {
+                client.record_result(idx, &res);
+                res
             })
     }
 
     pub fn uri_for(&self, path: &str) -> Url {
-        let mut url = self.base_uri.clone();
+        let mut url = self.current_endpoint();
         url.path_segments_mut()
             .map_err(|_| "cannot be base")
             .unwrap()
@@ -210,22 +387,71 @@ impl Client {
         let mut uri = self.uri_for("burst");
         uri.set_query(Some(&query));
 
+        let client = self.clone();
+        let idx = self.active.load(AtomicOrdering::SeqCst);
         self.inner
             .post(uri)
             .headers(headers)
             .send()
+            .from_err::<FetchError>()
             .and_then(|mut res| {
+                if res.status().is_server_error() {
+                    return Either::A(future::err(FetchError::Transient(
+                        TransientError::ServerError(res.status().as_u16()),
+                    )));
+                }
                 let body = mem::replace(res.body_mut(), Decoder::empty());
-                body.concat2()
+                Either::B(
+                    body.concat2()
+                        .from_err::<FetchError>()
+                        .and_then(|body| parse_json_result(&body)),
+                )
             })
-            .from_err::<FetchError>()
-            .and_then(|body| match parse_json_result(&body) {
-                Ok(x) => Ok(x),
-                Err(e) => Err(e.into()),
+            .then(move |res| {
+                client.record_result(idx, &res);
+                res
             })
     }
 }
 
+/// tries endpoints strictly preferred over the currently active one,
+/// starting from the most preferred (index 0), and fails back to the
+/// first one that responds.
+fn try_failback(client: Client, idx: usize) -> Box<dyn Future<Item = (), Error = ()> + Send> {
+    if idx >= client.active.load(AtomicOrdering::SeqCst) {
+        return Box::new(future::ok(()));
+    }
+    let probe_client = client.clone();
+    Box::new(client.probe(idx).then(move |res| -> Box<dyn Future<Item = (), Error = ()> + Send> {
+        if res.is_ok() {
+            let endpoint = &probe_client.endpoints[idx];
+            endpoint.consecutive_failures.store(0, AtomicOrdering::SeqCst);
+            endpoint.healthy.store(true, AtomicOrdering::SeqCst);
+            probe_client.active.store(idx, AtomicOrdering::SeqCst);
+            info!(
+                "pool failback: higher-priority endpoint {} is back up, switching to it",
+                endpoint.url
+            );
+            Box::new(future::ok(()))
+        } else {
+            Box::new(try_failback(probe_client, idx + 1))
+        }
+    }))
+}
+
+fn probe_loop(client: Client, executor: TaskExecutor, interval_ms: u64) {
+    let next_client = client.clone();
+    let next_executor = executor.clone();
+    let fut = Delay::new(Instant::now() + Duration::from_millis(interval_ms))
+        .map_err(|e| error!("pool health probe timer errored: {:?}", e))
+        .and_then(move |_| try_failback(client, 0))
+        .then(move |_| {
+            probe_loop(next_client, next_executor, interval_ms);
+            future::ok(())
+        });
+    executor.spawn(fut);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -19,8 +19,11 @@ pub struct Cfg {
     #[serde(default = "default_blocktime")]
     pub blocktime: u64,
 
-    #[serde(with = "url_serde")]
-    pub url: Url,
+    /// pool/proxy endpoints in priority order; the first is used until it
+    /// becomes unhealthy, at which point `Client` fails over to the next.
+    /// Accepts either a single URL (as before) or a list of URLs.
+    #[serde(rename = "url", with = "urls_serde")]
+    pub urls: Vec<Url>,
 
     #[serde(default = "default_gpus")]
     pub gpus: Vec<GpuConfig>,
@@ -31,17 +34,55 @@ pub struct Cfg {
     #[serde(default = "default_cpu_task_size")]
     pub cpu_worker_task_size: u64,
 
+    /// pin each cpu worker to its own core, weighted by per-cluster capacity
+    /// on heterogeneous (big.LITTLE-style) chips - see `affinity::detect_topology`.
     #[serde(default = "default_cpu_thread_pinning")]
     pub cpu_thread_pinning: bool,
 
+    #[serde(default = "default_cpu_pipeline")]
+    pub cpu_pipeline: bool,
+
     #[serde(default = "default_target_deadline")]
     pub target_deadline: u64,
 
     #[serde(default = "default_get_mining_info_interval")]
     pub get_mining_info_interval: u64,
 
-    #[serde(default = "default_timeout")]
-    pub timeout: u64,
+    /// use a long-lived push connection for new-job notifications instead
+    /// of waiting for the next `get_mining_info` poll; the poll keeps
+    /// running alongside it as a keep-alive/fallback either way. Requires
+    /// pool support.
+    #[serde(default = "default_push_enabled")]
+    pub push_enabled: bool,
+
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout: u64,
+
+    #[serde(default = "default_request_timeout")]
+    pub request_timeout: u64,
+
+    /// how often, in ms, to re-probe higher-priority pool/proxy endpoints
+    /// and fail back to the most-preferred one that responds.
+    #[serde(default = "default_pool_probe_interval")]
+    pub pool_probe_interval: u64,
+
+    #[serde(default = "default_network_threads")]
+    pub network_threads: usize,
+
+    /// how often, in ms, to print a rolling scan-rate/deadline/submission
+    /// summary, logged at info level.
+    #[serde(default = "default_stats_interval")]
+    pub stats_interval: u64,
+
+    /// how many times the miner will re-submit a deadline for the current
+    /// block that hasn't been confirmed yet, before giving up on it.
+    #[serde(default = "default_submission_max_retries")]
+    pub submission_max_retries: u32,
+
+    /// address to bind the JSON-RPC monitoring/control server to, e.g.
+    /// "127.0.0.1:1917"; empty disables it.
+    #[serde(default = "default_rpc_bind")]
+    pub rpc_bind: String,
 
     #[serde(default = "default_send_proxy_details")]
     pub send_proxy_details: bool,
@@ -98,6 +139,10 @@ fn default_cpu_thread_pinning() -> bool {
     false
 }
 
+fn default_cpu_pipeline() -> bool {
+    false
+}
+
 fn default_gpus() -> Vec<GpuConfig> {
     Vec::new()
 }
@@ -110,10 +155,38 @@ fn default_get_mining_info_interval() -> u64 {
     3000
 }
 
-fn default_timeout() -> u64 {
+fn default_push_enabled() -> bool {
+    false
+}
+
+fn default_connect_timeout() -> u64 {
     5000
 }
 
+fn default_request_timeout() -> u64 {
+    5000
+}
+
+fn default_pool_probe_interval() -> u64 {
+    60_000
+}
+
+fn default_network_threads() -> usize {
+    num_cpus::get()
+}
+
+fn default_stats_interval() -> u64 {
+    20_000
+}
+
+fn default_submission_max_retries() -> u32 {
+    10
+}
+
+fn default_rpc_bind() -> String {
+    "".to_owned()
+}
+
 fn default_send_proxy_details() -> bool {
     false
 }
@@ -146,6 +219,41 @@ fn default_logfile_log_pattern() -> String {
     "\r{d(%Y-%m-%dT%H:%M:%S.%3f%z)} [{h({l}):<5}] [{T}] [{f}:{L}] [{t}] - {M}:{m}{n}".to_owned()
 }
 
+/// (de)serializes `Cfg::urls` from either a single URL string (the
+/// historical shape of the `url` key) or a list of URL strings, in
+/// priority order.
+mod urls_serde {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use url::Url;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Url>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(s) => vec![s],
+            OneOrMany::Many(v) => v,
+        };
+        raw.into_iter()
+            .map(|s| Url::parse(&s).map_err(serde::de::Error::custom))
+            .collect()
+    }
+
+    pub fn serialize<S>(urls: &[Url], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        urls.iter().map(Url::to_string).collect::<Vec<_>>().serialize(serializer)
+    }
+}
+
 pub fn load_cfg(config: &str) -> Cfg {
     let cfg_str =
         fs::read_to_string(config).expect(&format!("failed to open config, config={}", config));
@@ -160,6 +268,6 @@ mod tests {
     #[test]
     fn test_load_cfg() {
         let cfg = load_cfg("config.yaml");
-        assert_eq!(cfg.timeout, 3000);
+        assert_eq!(cfg.request_timeout, 3000);
     }
 }
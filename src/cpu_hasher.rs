@@ -3,11 +3,13 @@ use crate::miner::NonceData;
 use crate::poc_hashing::find_best_deadline_rust;
 use crate::poc_hashing::noncegen_rust;
 use crate::poc_hashing::NONCE_SIZE;
-use crate::scheduler::{HasherMessage, RoundInfo};
-use crossbeam_channel::Sender;
+use crate::scheduler::{DeviceId, HasherMessage, RoundInfo};
+use crossbeam_channel::{Receiver, Sender};
 use futures::sync::mpsc;
 use libc::{c_void, uint64_t};
+use std::thread;
 use std::u64;
+use stopwatch::Stopwatch;
 
 #[derive(Debug, Clone)]
 pub enum SimdExtension {
@@ -15,9 +17,49 @@ pub enum SimdExtension {
     AVX2,
     AVX,
     SSE2,
+    NEON,
     None,
 }
 
+impl SimdExtension {
+    /// how many nonces the underlying `noncegen_*`/`find_best_deadline_*`
+    /// pair processes per call - each extension packs that many lanes of a
+    /// 32-bit Shabal word into one SIMD register. `local_nonces` must be a
+    /// multiple of this, or the generator walks past the nonce it was told
+    /// to stop at and writes past the end of `generate_buffer`'s allocation
+    /// (`task.local_nonces * NONCE_SIZE`, sized with no slack).
+    pub fn lanes(&self) -> u64 {
+        match self {
+            SimdExtension::AVX512f => 16,
+            SimdExtension::AVX2 => 8,
+            SimdExtension::AVX => 4,
+            SimdExtension::SSE2 => 4,
+            SimdExtension::NEON => 4,
+            SimdExtension::None => 1,
+        }
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+extern "C" {
+    pub fn init_shabal_neon();
+    pub fn noncegen_neon(
+        cache: *mut c_void,
+        numeric_ID: uint64_t,
+        local_startnonce: uint64_t,
+        local_nonces: uint64_t,
+    );
+    pub fn find_best_deadline_neon(
+        data: *const c_void,
+        scoop: uint64_t,
+        nonce_count: uint64_t,
+        gensig: *const c_void,
+        best_deadline: *mut uint64_t,
+        best_offset: *mut uint64_t,
+    ) -> ();
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 extern "C" {
     pub fn init_shabal_sse2();
     pub fn init_shabal_avx();
@@ -81,11 +123,16 @@ extern "C" {
     ) -> ();
 }
 
+#[derive(Clone)]
 pub struct CpuTask {
+    pub worker_id: usize,
     pub numeric_id: u64,
     pub local_startnonce: u64,
     pub local_nonces: u64,
     pub round: RoundInfo,
+    /// physical core this worker should be pinned to, if `cpu_thread_pinning` is
+    /// enabled; `None` leaves the OS scheduler free to move it.
+    pub core_id: Option<usize>,
 }
 
 #[derive(Clone)]
@@ -100,6 +147,19 @@ pub struct DeadlineHashingTask {
     pub number_of_warps: u64,
 }
 
+#[cfg(target_arch = "aarch64")]
+pub fn init_cpu_extensions() -> SimdExtension {
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        unsafe {
+            init_shabal_neon();
+        }
+        SimdExtension::NEON
+    } else {
+        SimdExtension::None
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 pub fn init_cpu_extensions() -> SimdExtension {
     if is_x86_feature_detected!("avx512f") {
         unsafe {
@@ -126,115 +186,305 @@ pub fn init_cpu_extensions() -> SimdExtension {
     }
 }
 
+// generates nonces for `task` into a freshly allocated page-aligned buffer.
+fn generate_buffer(task: &CpuTask, simd_ext: &SimdExtension) -> PageAlignedByteBuffer {
+    let buffer = PageAlignedByteBuffer::new(task.local_nonces as usize * NONCE_SIZE);
+    let bs = buffer.get_buffer();
+    let mut bs = bs.lock().unwrap();
+    unsafe {
+        match simd_ext {
+            SimdExtension::AVX512f => noncegen_avx512f(
+                bs.as_mut_ptr() as *mut c_void,
+                task.numeric_id,
+                task.local_startnonce,
+                task.local_nonces,
+            ),
+            SimdExtension::AVX2 => noncegen_avx2(
+                bs.as_mut_ptr() as *mut c_void,
+                task.numeric_id,
+                task.local_startnonce,
+                task.local_nonces,
+            ),
+            SimdExtension::AVX => noncegen_avx(
+                bs.as_mut_ptr() as *mut c_void,
+                task.numeric_id,
+                task.local_startnonce,
+                task.local_nonces,
+            ),
+            SimdExtension::SSE2 => noncegen_sse2(
+                bs.as_mut_ptr() as *mut c_void,
+                task.numeric_id,
+                task.local_startnonce,
+                task.local_nonces,
+            ),
+            #[cfg(target_arch = "aarch64")]
+            SimdExtension::NEON => noncegen_neon(
+                bs.as_mut_ptr() as *mut c_void,
+                task.numeric_id,
+                task.local_startnonce,
+                task.local_nonces,
+            ),
+            _ => noncegen_rust(
+                &mut bs[..],
+                task.numeric_id,
+                task.local_startnonce,
+                task.local_nonces,
+            ),
+        }
+    }
+    drop(bs);
+    buffer
+}
+
+// scans a generated buffer for the best deadline at `task.round.scoop`.
+fn scan_buffer(buffer: &PageAlignedByteBuffer, task: &CpuTask, simd_ext: &SimdExtension) -> (u64, u64) {
+    let bs = buffer.get_buffer();
+    let bs = bs.lock().unwrap();
+
+    #[allow(unused_assignments)]
+    let mut deadline: u64 = u64::MAX;
+    #[allow(unused_assignments)]
+    let mut offset: u64 = 0;
+
+    unsafe {
+        match simd_ext {
+            SimdExtension::AVX512f => find_best_deadline_avx512f(
+                bs.as_ptr() as *const c_void,
+                task.round.scoop,
+                task.local_nonces,
+                task.round.gensig.as_ptr() as *const c_void,
+                &mut deadline,
+                &mut offset,
+            ),
+            SimdExtension::AVX2 => find_best_deadline_avx2(
+                bs.as_ptr() as *const c_void,
+                task.round.scoop,
+                task.local_nonces,
+                task.round.gensig.as_ptr() as *const c_void,
+                &mut deadline,
+                &mut offset,
+            ),
+            SimdExtension::AVX => find_best_deadline_avx(
+                bs.as_ptr() as *const c_void,
+                task.round.scoop,
+                task.local_nonces,
+                task.round.gensig.as_ptr() as *const c_void,
+                &mut deadline,
+                &mut offset,
+            ),
+            SimdExtension::SSE2 => find_best_deadline_sse2(
+                bs.as_ptr() as *const c_void,
+                task.round.scoop,
+                task.local_nonces,
+                task.round.gensig.as_ptr() as *const c_void,
+                &mut deadline,
+                &mut offset,
+            ),
+            #[cfg(target_arch = "aarch64")]
+            SimdExtension::NEON => find_best_deadline_neon(
+                bs.as_ptr() as *const c_void,
+                task.round.scoop,
+                task.local_nonces,
+                task.round.gensig.as_ptr() as *const c_void,
+                &mut deadline,
+                &mut offset,
+            ),
+            _ => {
+                let result =
+                    find_best_deadline_rust(&bs, task.round.scoop, task.local_nonces, &task.round.gensig);
+                deadline = result.0;
+                offset = result.1;
+            }
+        }
+    }
+    (deadline, offset)
+}
+
+// reports a completed chunk (deadline found, nonces processed, measured
+// throughput) and asks the scheduler for the next one.
+fn report_result(tx: &Sender<HasherMessage>, task: &CpuTask, deadline: u64, offset: u64, elapsed_ms: u64) {
+    tx.send(HasherMessage::SubmitDeadline((
+        task.round.height,
+        task.local_startnonce + offset,
+        deadline,
+    )))
+    .expect("CPU task can't communicate with scheduler thread.");
+
+    tx.send(HasherMessage::NoncesProcessed(task.local_nonces))
+        .expect("CPU task can't communicate with scheduler thread.");
+    tx.send(HasherMessage::WorkReport {
+        device: DeviceId::Cpu(task.worker_id),
+        nonces: task.local_nonces,
+        elapsed_ms,
+    })
+    .expect("CPU task can't communicate with scheduler thread.");
+    tx.send(HasherMessage::CpuRequestForWork(task.worker_id))
+        .expect("CPU task can't communicate with scheduler thread.");
+}
+
+// number of nonces hashed per SIMD path during `--benchmark`: large enough
+// to amortize noncegen/scan overhead, small enough that a full run across
+// every path finishes in a few seconds.
+const BENCHMARK_NONCES: u64 = 256;
+
+/// synthetic round info used only to exercise the deadline-scan kernels
+/// during `--benchmark`; no submission happens so the actual values don't
+/// matter.
+fn benchmark_round() -> RoundInfo {
+    RoundInfo {
+        gensig: [0u8; 32],
+        base_target: 1,
+        scoop: 0,
+        height: 0,
+    }
+}
+
+/// runs noncegen + deadline-scan for `simd_ext` over a fixed synthetic
+/// nonce range and returns (nonces/sec, MiB/sec).
+fn benchmark_simd_ext(simd_ext: &SimdExtension) -> (f64, f64) {
+    let task = CpuTask {
+        worker_id: 0,
+        numeric_id: 0,
+        local_startnonce: 0,
+        local_nonces: BENCHMARK_NONCES,
+        round: benchmark_round(),
+        core_id: None,
+    };
+
+    let sw = Stopwatch::start_new();
+    let buffer = generate_buffer(&task, simd_ext);
+    scan_buffer(&buffer, &task, simd_ext);
+    let elapsed_s = (sw.elapsed_ms() as f64 / 1000.0).max(0.001);
+
+    let nonces_per_sec = BENCHMARK_NONCES as f64 / elapsed_s;
+    let mib_per_sec = nonces_per_sec * NONCE_SIZE as f64 / 1024.0 / 1024.0;
+    (nonces_per_sec, mib_per_sec)
+}
+
+/// drives every SIMD path this CPU supports, plus the scalar Rust
+/// fallback, over a fixed synthetic nonce range and logs measured
+/// nonces/sec and MiB/s for each. Used by `--benchmark` to let users
+/// compare hashing throughput and confirm the right path was selected,
+/// independent of any pool connection.
+pub fn run_benchmark() {
+    let mut extensions = vec![SimdExtension::None];
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            unsafe {
+                init_shabal_neon();
+            }
+            extensions.push(SimdExtension::NEON);
+        }
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("sse2") {
+            unsafe {
+                init_shabal_sse2();
+            }
+            extensions.push(SimdExtension::SSE2);
+        }
+        if is_x86_feature_detected!("avx") {
+            unsafe {
+                init_shabal_avx();
+            }
+            extensions.push(SimdExtension::AVX);
+        }
+        if is_x86_feature_detected!("avx2") {
+            unsafe {
+                init_shabal_avx2();
+            }
+            extensions.push(SimdExtension::AVX2);
+        }
+        if is_x86_feature_detected!("avx512f") {
+            unsafe {
+                init_shabal_avx512f();
+            }
+            extensions.push(SimdExtension::AVX512f);
+        }
+    }
+
+    info!(
+        "benchmark: {} nonces per path ({:.1} MiB)",
+        BENCHMARK_NONCES,
+        BENCHMARK_NONCES as f64 * NONCE_SIZE as f64 / 1024.0 / 1024.0
+    );
+    for ext in &extensions {
+        let (nonces_per_sec, mib_per_sec) = benchmark_simd_ext(ext);
+        info!(
+            "benchmark: {:>8?}  {:>10.1} nonces/s  {:>8.1} MiB/s",
+            ext, nonces_per_sec, mib_per_sec
+        );
+    }
+}
+
 pub fn hash_cpu(
     tx: Sender<HasherMessage>,
     hasher_task: CpuTask,
     simd_ext: SimdExtension,
 ) -> impl FnOnce() {
     move || {
-        // alloc
-        let buffer = PageAlignedByteBuffer::new(hasher_task.local_nonces as usize * NONCE_SIZE);
-        let bs = buffer.get_buffer();
-        let mut bs = bs.lock().unwrap();
-        unsafe {
-            match simd_ext {
-                SimdExtension::AVX512f => noncegen_avx512f(
-                    bs.as_mut_ptr() as *mut c_void,
-                    hasher_task.numeric_id,
-                    hasher_task.local_startnonce,
-                    hasher_task.local_nonces,
-                ),
-                SimdExtension::AVX2 => noncegen_avx2(
-                    bs.as_mut_ptr() as *mut c_void,
-                    hasher_task.numeric_id,
-                    hasher_task.local_startnonce,
-                    hasher_task.local_nonces,
-                ),
-                SimdExtension::AVX => noncegen_avx(
-                    bs.as_mut_ptr() as *mut c_void,
-                    hasher_task.numeric_id,
-                    hasher_task.local_startnonce,
-                    hasher_task.local_nonces,
-                ),
-                SimdExtension::SSE2 => noncegen_sse2(
-                    bs.as_mut_ptr() as *mut c_void,
-                    hasher_task.numeric_id,
-                    hasher_task.local_startnonce,
-                    hasher_task.local_nonces,
-                ),
-                _ => noncegen_rust(
-                    &mut bs[..],
-                    hasher_task.numeric_id,
-                    hasher_task.local_startnonce,
-                    hasher_task.local_nonces,
-                ),
-            }
+        if let Some(core_id) = hasher_task.core_id {
+            crate::affinity::pin_current_thread(core_id);
         }
 
-        // calc best deadline
-        #[allow(unused_assignments)]
-        let mut deadline: u64 = u64::MAX;
-        #[allow(unused_assignments)]
-        let mut offset: u64 = 0;
+        let sw = Stopwatch::start_new();
+        let buffer = generate_buffer(&hasher_task, &simd_ext);
+        let (deadline, offset) = scan_buffer(&buffer, &hasher_task, &simd_ext);
+        report_result(&tx, &hasher_task, deadline, offset, sw.elapsed_ms() as u64);
+    }
+}
 
-        unsafe {
-            match simd_ext {
-                SimdExtension::AVX512f => find_best_deadline_avx512f(
-                    bs.as_ptr() as *const c_void,
-                    hasher_task.round.scoop,
-                    hasher_task.local_nonces,
-                    hasher_task.round.gensig.as_ptr() as *const c_void,
-                    &mut deadline,
-                    &mut offset,
-                ),
-                SimdExtension::AVX2 => find_best_deadline_avx2(
-                    bs.as_ptr() as *const c_void,
-                    hasher_task.round.scoop,
-                    hasher_task.local_nonces,
-                    hasher_task.round.gensig.as_ptr() as *const c_void,
-                    &mut deadline,
-                    &mut offset,
-                ),
-                SimdExtension::AVX => find_best_deadline_avx(
-                    bs.as_ptr() as *const c_void,
-                    hasher_task.round.scoop,
-                    hasher_task.local_nonces,
-                    hasher_task.round.gensig.as_ptr() as *const c_void,
-                    &mut deadline,
-                    &mut offset,
-                ),
-                SimdExtension::SSE2 => find_best_deadline_sse2(
-                    bs.as_ptr() as *const c_void,
-                    hasher_task.round.scoop,
-                    hasher_task.local_nonces,
-                    hasher_task.round.gensig.as_ptr() as *const c_void,
-                    &mut deadline,
-                    &mut offset,
-                ),
-                _ => {
-                    let result = find_best_deadline_rust(
-                        &bs,
-                        hasher_task.round.scoop,
-                        hasher_task.local_nonces,
-                        &hasher_task.round.gensig,
-                    );
-                    deadline = result.0;
-                    offset = result.1;
-                }
-            }
+/// Pipelined CPU worker: a long-lived thread (mirroring the GPU hasher
+/// thread) that owns two page-aligned buffers and overlaps generation of
+/// the next chunk with the deadline scan of the chunk before it, since the
+/// scan only touches one scoop and is cheap relative to generation.
+pub fn hash_cpu_pipelined(
+    tx: Sender<HasherMessage>,
+    rx_task: Receiver<Option<CpuTask>>,
+    simd_ext: SimdExtension,
+    core_id: Option<usize>,
+) -> impl FnOnce() {
+    move || {
+        if let Some(core_id) = core_id {
+            crate::affinity::pin_current_thread(core_id);
         }
 
-        // report hashing done
-        tx.send(HasherMessage::SubmitDeadline((
-            hasher_task.round.height,
-            hasher_task.local_startnonce + offset,
-            deadline,
-        )))
-        .expect("GPU task can't communicate with scheduler thread.");
+        let mut in_flight: Option<(PageAlignedByteBuffer, CpuTask, Stopwatch)> = None;
+
+        for task in &rx_task {
+            let task = match task {
+                Some(task) => task,
+                None => break,
+            };
+
+            let gen_task = task.clone();
+            let gen_simd_ext = simd_ext.clone();
+            let sw = Stopwatch::start_new();
+            let gen_handle = thread::spawn(move || generate_buffer(&gen_task, &gen_simd_ext));
 
-        tx.send(HasherMessage::NoncesProcessed(hasher_task.local_nonces))
-            .expect("GPU task can't communicate with scheduler thread.");
-        tx.send(HasherMessage::CpuRequestForWork)
-            .expect("GPU task can't communicate with scheduler thread.");
+            // scan of the previous chunk runs concurrently with generation
+            // of this one on the background thread spawned above.
+            if let Some((prev_buffer, prev_task, prev_sw)) = in_flight.take() {
+                let (deadline, offset) = scan_buffer(&prev_buffer, &prev_task, &simd_ext);
+                report_result(&tx, &prev_task, deadline, offset, prev_sw.elapsed_ms() as u64);
+            } else {
+                // nothing to report yet: ask for the next chunk now so there
+                // is one to overlap with this one.
+                tx.send(HasherMessage::CpuRequestForWork(task.worker_id))
+                    .expect("CPU task can't communicate with scheduler thread.");
+            }
+
+            let buffer = gen_handle.join().expect("cpu generation thread panicked");
+            in_flight = Some((buffer, task, sw));
+        }
+
+        if let Some((buffer, task, sw)) = in_flight.take() {
+            let (deadline, offset) = scan_buffer(&buffer, &task, &simd_ext);
+            report_result(&tx, &task, deadline, offset, sw.elapsed_ms() as u64);
+        }
     }
 }
@@ -1,7 +1,10 @@
-use crate::ocl::{gpu_hash, GpuContext};
-use crate::scheduler::{HasherMessage, RoundInfo};
+use crate::ocl::{gpu_finish, gpu_hash, gpu_init, gpu_submit_noncegen, GpuConfig, GpuContext, PendingNoncegen};
+use crate::poc_hashing::NONCE_SIZE;
+use crate::scheduler::{DeviceId, HasherMessage, RoundInfo};
 use crossbeam_channel::{Receiver, Sender};
+use std::cmp::max;
 use std::sync::Arc;
+use stopwatch::Stopwatch;
 
 pub struct GpuTask {
     pub numeric_id: u64,
@@ -10,6 +13,96 @@ pub struct GpuTask {
     pub round: RoundInfo,
 }
 
+/// drives the noncegen + deadline-scan kernels for each configured GPU over
+/// a fixed synthetic nonce range (one full work-group launch) and logs
+/// measured nonces/sec and MiB/s. Used by `--benchmark`, independent of
+/// any pool connection.
+pub fn run_benchmark(gpus: &[GpuConfig]) {
+    for (i, gpu) in gpus.iter().enumerate() {
+        let contexts = gpu_init(std::slice::from_ref(gpu));
+        let gpu_context = &contexts[0];
+        let nonces = gpu_context.worksize as u64;
+
+        let task = GpuTask {
+            numeric_id: 0,
+            local_startnonce: 0,
+            local_nonces: nonces,
+            round: RoundInfo {
+                gensig: [0u8; 32],
+                base_target: 1,
+                scoop: 0,
+                height: 0,
+            },
+        };
+
+        let sw = Stopwatch::start_new();
+        let (_, _, timings) = gpu_hash(gpu_context, &task);
+        let elapsed_s = (sw.elapsed_ms() as f64 / 1000.0).max(0.001);
+
+        let nonces_per_sec = nonces as f64 / elapsed_s;
+        let mib_per_sec = nonces_per_sec * NONCE_SIZE as f64 / 1024.0 / 1024.0;
+        info!(
+            "benchmark: gpu {}  {:>10.1} nonces/s  {:>8.1} MiB/s",
+            i, nonces_per_sec, mib_per_sec
+        );
+        info!(
+            "benchmark: gpu {}  noncegen={:.2}ms  calculate_deadlines={:.2}ms  find_min={:.2}ms",
+            i,
+            timings.noncegen_ns as f64 / 1_000_000.0,
+            timings.calculate_deadlines_ns as f64 / 1_000_000.0,
+            timings.find_min_ns as f64 / 1_000_000.0,
+        );
+    }
+}
+
+/// reports a finished task's result/timings to the scheduler thread, then
+/// requests the next one - shared by the steady-state and drain paths below.
+fn report_finished(
+    tx: &Sender<HasherMessage>,
+    gpu_id: usize,
+    task: &GpuTask,
+    deadline: u64,
+    offset: u64,
+    timings: crate::ocl::GpuKernelTimings,
+) {
+    tx.send(HasherMessage::NoncesProcessed(task.local_nonces))
+        .expect("GPU task can't communicate with scheduler thread.");
+
+    // nanosecond-accurate on-device time, from the profiling events
+    // gpu_finish captured, rather than host wall time.
+    let kernel_ns = timings.noncegen_ns + timings.calculate_deadlines_ns + timings.find_min_ns;
+    tx.send(HasherMessage::WorkReport {
+        device: DeviceId::Gpu(gpu_id),
+        nonces: task.local_nonces,
+        elapsed_ms: max(1, kernel_ns / 1_000_000),
+    })
+    .expect("GPU task can't communicate with scheduler thread.");
+
+    tx.send(HasherMessage::GpuKernelTimings {
+        device: DeviceId::Gpu(gpu_id),
+        noncegen_ns: timings.noncegen_ns,
+        calculate_deadlines_ns: timings.calculate_deadlines_ns,
+        find_min_ns: timings.find_min_ns,
+    })
+    .expect("GPU task can't communicate with scheduler thread.");
+
+    tx.send(HasherMessage::SubmitDeadline((
+        task.round.height,
+        task.local_startnonce + offset,
+        deadline,
+        task.round.block,
+    )))
+    .expect("GPU task can't communicate with scheduler thread.");
+
+    tx.send(HasherMessage::GpuRequestForWork(gpu_id))
+        .expect("GPU task can't communicate with scheduler thread.");
+}
+
+/// double-buffered and pipelined, same idea as `cpu_hasher`'s
+/// `hash_cpu_pipelined`: a task's `noncegen` is submitted into one buffer
+/// slot before the previous task's `calculate_deadlines`/`find_min`/readback
+/// (against the other slot) is awaited, so the driver overlaps compute with
+/// readback instead of the host forcing a `finish` between every task.
 pub fn create_gpu_hasher_thread(
     gpu_id: usize,
     gpu_context: Arc<GpuContext>,
@@ -17,28 +110,29 @@ pub fn create_gpu_hasher_thread(
     rx_hasher_task: Receiver<Option<GpuTask>>,
 ) -> impl FnOnce() {
     move || {
+        let mut in_flight: Option<(GpuTask, PendingNoncegen)> = None;
+        let mut buffer_idx = 0;
+
         for task in rx_hasher_task {
             // check if new task or termination
             match task {
                 // new task
                 Some(task) => {
-                    // gpu generate nonces
-                    let (deadline, offset) = gpu_hash(&gpu_context, &task);
-
-                    // report hashing done
-                    tx.send(HasherMessage::NoncesProcessed(task.local_nonces))
-                        .expect("GPU task can't communicate with scheduler thread.");
-
-                    tx.send(HasherMessage::SubmitDeadline((
-                        task.round.height,
-                        task.local_startnonce + offset,
-                        deadline,
-                        task.round.block,
-                    )))
-                    .expect("GPU task can't communicate with scheduler thread.");
-
-                    tx.send(HasherMessage::GpuRequestForWork(gpu_id))
-                        .expect("GPU task can't communicate with scheduler thread.");
+                    let pending = gpu_submit_noncegen(&gpu_context, buffer_idx, &task);
+
+                    if let Some((prev_task, prev_pending)) = in_flight.take() {
+                        let (deadline, offset, timings) =
+                            gpu_finish(&gpu_context, prev_pending, &prev_task);
+                        report_finished(&tx, gpu_id, &prev_task, deadline, offset, timings);
+                    } else {
+                        // nothing to finish yet: ask for the next task now so
+                        // there is one to overlap with this one.
+                        tx.send(HasherMessage::GpuRequestForWork(gpu_id))
+                            .expect("GPU task can't communicate with scheduler thread.");
+                    }
+
+                    buffer_idx = 1 - buffer_idx;
+                    in_flight = Some((task, pending));
                 }
                 // termination
                 None => {
@@ -46,5 +140,11 @@ pub fn create_gpu_hasher_thread(
                 }
             }
         }
+
+        // drain the last submitted task, which hasn't been finished yet.
+        if let Some((task, pending)) = in_flight.take() {
+            let (deadline, offset, timings) = gpu_finish(&gpu_context, pending, &task);
+            report_finished(&tx, gpu_id, &task, deadline, offset, timings);
+        }
     }
 }
@@ -6,6 +6,7 @@ extern crate clap;
 #[macro_use]
 extern crate log;
 
+mod affinity;
 mod com;
 mod future;
 mod buffer;
@@ -18,16 +19,19 @@ mod miner;
 #[cfg(feature = "opencl")]
 mod ocl;
 mod poc_hashing;
+mod push;
 mod request;
+mod rpc;
 mod scheduler;
 mod shabal256;
 
 use crate::config::load_cfg;
 use crate::cpu_hasher::{init_cpu_extensions, SimdExtension};
 use crate::miner::Miner;
+use crate::poc_hashing::NONCE_SIZE;
 use clap::{App, Arg};
 use futures::Future;
-use std::cmp::min;
+use std::cmp::{max, min};
 use std::process;
 use tokio::runtime::Builder;
 
@@ -56,13 +60,34 @@ fn main() {
             .help("Display OpenCL platforms and devices")
             .takes_value(false),
     );
+    let arg = arg.arg(
+        Arg::with_name("benchmark")
+            .long("benchmark")
+            .help("Measure hashing throughput of every available SIMD/GPU path and exit, without connecting to a pool")
+            .takes_value(false),
+    );
+    #[cfg(feature = "opencl")]
+    let arg = arg.arg(
+        Arg::with_name("gpu")
+            .long("gpu")
+            .value_name("MODE")
+            .help("Use 'auto' to benchmark every installed GPU instead of the platform/device indices in config.yaml")
+            .takes_value(true),
+    );
 
     let matches = &arg.get_matches();
     let config = matches.value_of("config").unwrap();
 
-    let cfg_loaded = load_cfg(config);
+    let mut cfg_loaded = load_cfg(config);
     logger::init_logger(&cfg_loaded);
 
+    #[cfg(feature = "opencl")]
+    {
+        if matches.value_of("gpu") == Some("auto") {
+            cfg_loaded.gpus = ocl::enumerate_gpus(false);
+        }
+    }
+
     info!("bencher v.{}", crate_version!());
 
     if matches.is_present("opencl") {
@@ -76,6 +101,14 @@ fn main() {
     let cpu_name = cpuid.get_extended_function_info().unwrap();
     let cpu_name = cpu_name.processor_brand_string().unwrap().trim();
 
+    if matches.is_present("benchmark") {
+        info!("benchmark: {} [{:?}]", cpu_name, &simd_extension);
+        cpu_hasher::run_benchmark();
+        #[cfg(feature = "opencl")]
+        gpu_hasher::run_benchmark(&cfg_loaded.gpus);
+        process::exit(0);
+    }
+
     #[cfg(not(feature = "opencl"))]
     let cpu_threads = if cfg_loaded.cpu_threads == 0 {
         num_cpus::get()
@@ -104,6 +137,20 @@ fn main() {
         &simd_extension
     );
 
+    let core_placement = if cfg_loaded.cpu_thread_pinning {
+        let clusters = affinity::detect_topology();
+        Some(affinity::place_workers(&clusters, cpu_threads))
+    } else {
+        None
+    };
+    if let Some(placement) = &core_placement {
+        info!(
+            "cpu-affinity: pinned {} workers to cores {:?}",
+            placement.len(),
+            placement.iter().map(|(id, _)| *id).collect::<Vec<usize>>()
+        );
+    }
+
     let mut cpu_string = format!(
         "cpu: {} [using {} of {} cores{}{:?}]",
         cpu_name,
@@ -130,7 +177,20 @@ fn main() {
         (0,"".to_owned())
     };
     cpu_string.push_str(&gpu_string);
-        
+
+    // pipelining keeps one extra in-flight buffer per cpu worker so
+    // generation of the next chunk can overlap the deadline scan of the
+    // last one (see `hash_cpu_pipelined`, a fixed 2-buffer depth); account
+    // for that the same way gpu_mem_needed does for GPUs.
+    let cpu_buffers = if cfg_loaded.cpu_pipeline { 2 } else { 1 };
+    let cpu_mem_needed =
+        cpu_threads as u64 * cfg_loaded.cpu_worker_task_size * NONCE_SIZE as u64 * cpu_buffers as u64;
+    info!(
+        "cpu-ram: Usage={:.2} MiB{}",
+        cpu_mem_needed as f64 / 1024.0 / 1024.0,
+        if cfg_loaded.cpu_pipeline { " (pipelined)" } else { "" }
+    );
+
     #[cfg(feature = "opencl")]
     info!("gpu extensions: OpenCL");
 
@@ -146,8 +206,26 @@ fn main() {
         }
     );
 
-    let rt = Builder::new().core_threads(1).build().unwrap();
-    let m = Miner::new(cfg_loaded, simd_extension, cpu_threads, rt.executor(), cpu_string);
-    m.run();
+    let network_threads = max(1, cfg_loaded.network_threads);
+    let submission_timeout = cfg_loaded.request_timeout;
+    info!("network: {} worker thread(s)", network_threads);
+
+    let rt = Builder::new().core_threads(network_threads).build().unwrap();
+    let m = Miner::new(cfg_loaded, simd_extension, cpu_threads, rt.executor(), cpu_string, core_placement);
+    let request_handler = m.request_handler();
+    let miner_handle = m.run();
+
+    // on Ctrl-C, stop the scheduler (abandoning the in-flight round instead
+    // of finishing the whole scan) and give in-flight nonce submissions up
+    // to `request_timeout` ms to complete before the runtime (and the process)
+    // goes down, instead of dropping them mid-flight.
+    ctrlc::set_handler(move || {
+        warn!("shutdown signal received, stopping miner...");
+        miner_handle.stop();
+        request_handler.drain(submission_timeout);
+        process::exit(0);
+    })
+    .expect("failed to set Ctrl-C handler");
+
     rt.shutdown_on_idle().wait().unwrap();
 }
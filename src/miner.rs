@@ -1,37 +1,171 @@
 use crate::com::api::MiningInfoResponse as MiningInfo;
 use crate::config::Cfg;
 use crate::cpu_hasher::SimdExtension;
-use crate::future::interval::Interval;
 #[cfg(feature = "opencl")]
 use crate::ocl::GpuConfig;
 use crate::poc_hashing;
-use crate::request::RequestHandler;
+use crate::request::{RequestHandler, StatEvent};
+use crate::rpc;
 use crate::scheduler::create_scheduler_thread;
-use crate::scheduler::RoundInfo;
-use crossbeam_channel::unbounded;
+use crate::scheduler::{RoundInfo, SchedulerStatus};
+use crossbeam_channel::{unbounded, Receiver as StatReceiver};
 use futures::sync::mpsc;
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::u64;
 use tokio::prelude::*;
 use tokio::runtime::TaskExecutor;
-use std::cmp::{max};
+use tokio::timer::Delay;
+use std::cmp::{max, min};
 
 const GENESIS_BASE_TARGET: u64 = 4_398_046_511_104;
+// cap on the get_mining_info poll backoff, so a long outage still retries
+// often enough to recover quickly once the pool is back.
+const MAX_GET_MINING_INFO_BACKOFF_MS: u64 = 60_000;
+// how often the pending-submission retry driver wakes up to check for
+// confirmations and due retries.
+const PENDING_SUBMISSION_POLL_INTERVAL_MS: u64 = 2_000;
+// base and cap for the pending-submission retry backoff: doubles on each
+// attempt, starting here, up to this ceiling.
+const PENDING_SUBMISSION_BASE_BACKOFF_MS: u64 = 2_000;
+const MAX_PENDING_SUBMISSION_BACKOFF_MS: u64 = 60_000;
 
 pub struct Miner {
     executor: TaskExecutor,
     request_handler: RequestHandler,
     cpu_threads: usize,
     cpu_worker_task_size: u64,
+    cpu_pipeline: bool,
     simd_extensions: SimdExtension,
     numeric_id: u64,
     start_nonce: u64,
-    target_deadline: u64,
+    target_deadline: Arc<AtomicU64>,
     blocktime: u64,
     gpus: Vec<GpuConfig>,
     get_mining_info_interval: u64,
+    core_placement: Option<Vec<(usize, u64)>>,
+    rpc_bind: String,
+    status: Arc<Mutex<SchedulerStatus>>,
+    paused: Arc<AtomicBool>,
+    stats_interval: u64,
+    rx_stats: StatReceiver<StatEvent>,
+    submission_max_retries: u32,
+    shutdown: Arc<AtomicBool>,
+    push_enabled: bool,
+}
+
+/// returned by `Miner::run`; lets a caller (e.g. a Ctrl-C handler) stop the
+/// miner cleanly instead of just killing the process. Stopping (1) halts
+/// the `get_mining_info` poll, (2) makes the scheduler thread abandon its
+/// current round instead of finishing the whole scoop scan, and (3) blocks
+/// until the scheduler thread - and the long-lived pipelined cpu and gpu
+/// hasher threads it owns - have actually exited. `hash_cpu` tasks already
+/// running on the non-pipelined cpu thread pool are not waited for.
+pub struct MinerHandle {
+    shutdown: Arc<AtomicBool>,
+    scheduler_thread: Mutex<Option<thread::JoinHandle<()>>>,
+}
+
+impl MinerHandle {
+    pub fn stop(&self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.scheduler_thread.lock().unwrap().take() {
+            handle.join().expect("scheduler thread panicked");
+        }
+    }
+}
+
+/// rolling submit/deadline counters for the periodic stats print task,
+/// reset after every print so the printed numbers always describe the
+/// interval just elapsed rather than the run as a whole.
+struct Statistics {
+    deadlines_found: u64,
+    best_deadline: u64,
+    confirmed: u64,
+    rejected: u64,
+}
+
+impl Default for Statistics {
+    fn default() -> Self {
+        Self {
+            deadlines_found: 0,
+            best_deadline: u64::MAX,
+            confirmed: 0,
+            rejected: 0,
+        }
+    }
+}
+
+impl Statistics {
+    fn reset(&mut self) {
+        *self = Statistics::default();
+    }
+}
+
+/// prints a one-line summary of scan rate (from the scheduler's own
+/// per-device totals, so this doesn't re-derive a second nonce counter)
+/// plus this interval's deadlines/submission outcomes, then resets the
+/// counters and reschedules itself - same recursive-`Delay` shape as
+/// `poll_mining_info`/`probe_loop`. `stats.confirmed`/`rejected` are folded
+/// in by `retry_pending_submissions`, the sole consumer of `rx_stats` -
+/// it also needs those events to track pending-submission confirmations.
+fn print_statistics(
+    executor: TaskExecutor,
+    stats: Arc<Mutex<Statistics>>,
+    status: Arc<Mutex<SchedulerStatus>>,
+    interval_ms: u64,
+) -> Box<dyn Future<Item = (), Error = ()> + Send> {
+    Box::new(
+        Delay::new(Instant::now() + Duration::from_millis(interval_ms))
+            .map_err(|e| error!("stats timer errored: {:?}", e))
+            .and_then(move |_| {
+                let nonces_per_sec: f64 = status
+                    .lock()
+                    .unwrap()
+                    .devices
+                    .values()
+                    .map(|d| d.nonces_per_sec)
+                    .sum();
+                let tib_per_min =
+                    nonces_per_sec * 60.0 * (poc_hashing::NONCE_SIZE as f64) / 1024.0 / 1024.0 / 1024.0 / 1024.0;
+
+                {
+                    let mut stats = stats.lock().unwrap();
+                    info!(
+                        "stats: scan={:.2} TiB/min ({:.0} nonces/s), deadlines found={} (best={}), submit confirmed={}, rejected={}",
+                        tib_per_min,
+                        nonces_per_sec,
+                        stats.deadlines_found,
+                        if stats.best_deadline == u64::MAX { 0 } else { stats.best_deadline },
+                        stats.confirmed,
+                        stats.rejected,
+                    );
+                    stats.reset();
+                }
+
+                executor.spawn(print_statistics(executor.clone(), stats, status, interval_ms));
+                future::ok(())
+            }),
+    )
+}
+
+/// a submitted deadline that hasn't been confirmed or rejected yet, kept
+/// around so `retry_pending_submissions` can re-send it if the pool never
+/// responds. Abandoned once the block it's keyed by in
+/// `State::pending_submissions` no longer matches `State::block`.
+struct PendingSubmission {
+    numeric_id: u64,
+    nonce: u64,
+    height: u64,
+    deadline_unadjusted: u64,
+    deadline_adjusted: u64,
+    gen_sig: [u8; 32],
+    attempt: u32,
+    next_retry_at: Instant,
 }
 
 pub struct State {
@@ -46,6 +180,9 @@ pub struct State {
     best_deadline: u64,
     scoop: u32,
     capacity: u64,
+    /// deadlines submitted for the current (or a just-superseded) block
+    /// that haven't been confirmed yet, keyed by block.
+    pending_submissions: HashMap<u64, PendingSubmission>,
 }
 
 impl State {
@@ -62,6 +199,7 @@ impl State {
             best_deadline: u64::MAX,
             scoop: 0,
             capacity: 0,
+            pending_submissions: HashMap::new(),
         }
     }
 
@@ -91,6 +229,125 @@ impl State {
     }
 }
 
+/// consumes `rx_stats` to confirm or abandon pending submissions, then
+/// re-sends any still-outstanding submission for the current block whose
+/// backoff has elapsed, up to `max_retries` attempts. This is a safety net
+/// on top of `RequestHandler`'s own `Transient`-error retry: it guards
+/// against a submission getting stuck (e.g. a response that never arrives)
+/// rather than against an explicit failure. Pending entries for a block
+/// other than `state.block` are dropped without resubmitting - the round
+/// moved on, so retrying them would only confuse the pool. Same
+/// recursive-`Delay` shape as `poll_mining_info`/`print_statistics`.
+fn retry_pending_submissions(
+    executor: TaskExecutor,
+    rx_stats: StatReceiver<StatEvent>,
+    state: Arc<Mutex<State>>,
+    stats: Arc<Mutex<Statistics>>,
+    request_handler: RequestHandler,
+    max_retries: u32,
+) -> Box<dyn Future<Item = (), Error = ()> + Send> {
+    Box::new(
+        Delay::new(Instant::now() + Duration::from_millis(PENDING_SUBMISSION_POLL_INTERVAL_MS))
+            .map_err(|e| error!("pending submission timer errored: {:?}", e))
+            .and_then(move |_| {
+                {
+                    let mut state = state.lock().unwrap();
+                    let mut stats = stats.lock().unwrap();
+                    for event in rx_stats.try_iter() {
+                        let (block, nonce) = match event {
+                            StatEvent::Confirmed { block, nonce } => {
+                                stats.confirmed += 1;
+                                (block, nonce)
+                            }
+                            StatEvent::Rejected { block, nonce } => {
+                                stats.rejected += 1;
+                                (block, nonce)
+                            }
+                        };
+                        let is_match = state
+                            .pending_submissions
+                            .get(&block)
+                            .map_or(false, |p| p.nonce == nonce);
+                        if is_match {
+                            state.pending_submissions.remove(&block);
+                        }
+                    }
+                }
+
+                {
+                    let mut state = state.lock().unwrap();
+                    let current_block = state.block;
+                    let now = Instant::now();
+                    let mut due: Vec<u64> = Vec::new();
+                    let mut stale: Vec<u64> = Vec::new();
+                    for (&block, pending) in state.pending_submissions.iter() {
+                        if block != current_block {
+                            stale.push(block);
+                        } else if now >= pending.next_retry_at {
+                            due.push(block);
+                        }
+                    }
+
+                    for block in stale {
+                        if let Some(pending) = state.pending_submissions.remove(&block) {
+                            warn!(
+                                "abandoning pending submission for superseded block: \
+                                 block={}, nonce={}, dl={}",
+                                block, pending.nonce, pending.deadline_adjusted
+                            );
+                        }
+                    }
+
+                    for block in due {
+                        let give_up = {
+                            let pending = state.pending_submissions.get(&block).unwrap();
+                            pending.attempt >= max_retries
+                        };
+                        if give_up {
+                            let pending = state.pending_submissions.remove(&block).unwrap();
+                            warn!(
+                                "giving up on unconfirmed submission after {} attempts: \
+                                 block={}, nonce={}, dl={}",
+                                pending.attempt, block, pending.nonce, pending.deadline_adjusted
+                            );
+                            continue;
+                        }
+                        let pending = state.pending_submissions.get_mut(&block).unwrap();
+                        let backoff_ms = min(
+                            MAX_PENDING_SUBMISSION_BACKOFF_MS,
+                            PENDING_SUBMISSION_BASE_BACKOFF_MS * (1u64 << pending.attempt),
+                        );
+                        pending.attempt += 1;
+                        pending.next_retry_at = now + Duration::from_millis(backoff_ms);
+                        warn!(
+                            "re-submitting unconfirmed deadline: block={}, nonce={}, dl={}, attempt={}",
+                            block, pending.nonce, pending.deadline_adjusted, pending.attempt
+                        );
+                        request_handler.submit_nonce(
+                            pending.numeric_id,
+                            pending.nonce,
+                            pending.height,
+                            block,
+                            pending.deadline_unadjusted,
+                            pending.deadline_adjusted,
+                            pending.gen_sig,
+                        );
+                    }
+                }
+
+                executor.spawn(retry_pending_submissions(
+                    executor.clone(),
+                    rx_stats,
+                    state,
+                    stats,
+                    request_handler,
+                    max_retries,
+                ));
+                future::ok(())
+            }),
+    )
+}
+
 #[derive(Clone)]
 pub struct NonceData {
     pub numeric_id: u64,
@@ -103,20 +360,145 @@ pub struct NonceData {
     pub base_target: u64
 }
 
+/// applies a freshly-fetched/pushed `MiningInfo` to `state` and, if it
+/// actually describes a new round (a changed generation signature), tells
+/// the hasher thread to start scanning it. Shared by `poll_mining_info` and
+/// `push::start_push_listener`'s `on_notify` callback, so a push
+/// notification and a poll response feed the exact same path.
+fn apply_new_mining_info(
+    state: &Arc<Mutex<State>>,
+    tx_rounds: &crossbeam_channel::Sender<RoundInfo>,
+    mining_info: MiningInfo,
+) {
+    let mut state = state.lock().unwrap();
+    state.first = false;
+    if state.outage {
+        error!("{: <80}", "outage resolved.");
+        state.outage = false;
+    }
+    if mining_info.generation_signature != state.generation_signature {
+        state.update_mining_info(&mining_info);
+
+        // communicate new round hasher
+        tx_rounds
+            .send(RoundInfo {
+                gensig: state.generation_signature_bytes,
+                base_target: state.base_target,
+                scoop: state.scoop.into(),
+                height: state.height,
+                block: state.block,
+            })
+            .expect("main thread can't communicate with hasher thread");
+    }
+}
+
+/// polls `get_mining_info` on a schedule that backs off on consecutive
+/// failures: starts at `base_interval_ms`, doubles on each failure up to
+/// `MAX_GET_MINING_INFO_BACKOFF_MS`, and applies full jitter (a uniform
+/// random wait in `[0, backoff_ms]`) once backed off past the base
+/// interval, so many miners hitting the same flaky pool don't retry in
+/// lockstep. Resets to `base_interval_ms` as soon as a request succeeds.
+fn poll_mining_info(
+    executor: TaskExecutor,
+    request_handler: RequestHandler,
+    state: Arc<Mutex<State>>,
+    tx_rounds: crossbeam_channel::Sender<RoundInfo>,
+    status: Arc<Mutex<SchedulerStatus>>,
+    base_interval_ms: u64,
+    backoff_ms: u64,
+    shutdown: Arc<AtomicBool>,
+) -> Box<dyn Future<Item = (), Error = ()> + Send> {
+    let wait_ms = if backoff_ms <= base_interval_ms {
+        backoff_ms
+    } else {
+        rand::thread_rng().gen_range(0, backoff_ms)
+    };
+
+    Box::new(
+        Delay::new(Instant::now() + Duration::from_millis(wait_ms))
+            .map_err(|e| error!("mining info poll timer errored: {:?}", e))
+            .and_then(move |_| -> Box<dyn Future<Item = (), Error = ()> + Send> {
+                if shutdown.load(Ordering::SeqCst) {
+                    // stop the interval: no more polling, no more rounds.
+                    return Box::new(future::ok(()));
+                }
+                let capacity = state.lock().unwrap().capacity;
+                Box::new(request_handler.clone().get_mining_info(capacity).then(move |mining_info| {
+                    {
+                        let mut status = status.lock().unwrap();
+                        status.active_endpoint = request_handler.current_endpoint().to_string();
+                        status.endpoints = request_handler
+                            .endpoint_health()
+                            .into_iter()
+                            .map(|(url, healthy)| (url.to_string(), healthy))
+                            .collect();
+                    }
+                    let next_backoff = match mining_info {
+                        Ok(mining_info) => {
+                            apply_new_mining_info(&state, &tx_rounds, mining_info);
+                            base_interval_ms
+                        }
+                        _ => {
+                            let mut state = state.lock().unwrap();
+                            if state.first {
+                                error!(
+                                    "{: <80}",
+                                    "error getting mining info, please check server config"
+                                );
+                                state.first = false;
+                                state.outage = true;
+                            } else {
+                                if !state.outage {
+                                    error!(
+                                        "{: <80}",
+                                        "error getting mining info => connection outage..."
+                                    );
+                                }
+                                state.outage = true;
+                            }
+                            min(
+                                MAX_GET_MINING_INFO_BACKOFF_MS,
+                                max(base_interval_ms, backoff_ms.saturating_mul(2)),
+                            )
+                        }
+                    };
+                    executor.spawn(poll_mining_info(
+                        executor.clone(),
+                        request_handler.clone(),
+                        state.clone(),
+                        tx_rounds.clone(),
+                        status.clone(),
+                        base_interval_ms,
+                        next_backoff,
+                        shutdown.clone(),
+                    ));
+                    future::ok(())
+                }))
+            }),
+    )
+}
+
 impl Miner {
     pub fn new(
         cfg: Cfg,
         simd_extensions: SimdExtension,
         cpu_threads: usize,
         executor: TaskExecutor,
+        core_placement: Option<Vec<(usize, u64)>>,
     ) -> Miner {
-        info!("server: {}", cfg.url);
+        info!("server: {}", cfg.urls[0]);
+        let status = Arc::new(Mutex::new(SchedulerStatus::default()));
+        status.lock().unwrap().active_endpoint = cfg.urls[0].to_string();
+        let (tx_stats, rx_stats) = unbounded();
         let request_handler = RequestHandler::new(
-            cfg.url,
+            cfg.urls,
             cfg.secret_phrase,
-            cfg.timeout,
+            cfg.connect_timeout,
+            cfg.request_timeout,
             cfg.send_proxy_details,
             cfg.additional_headers,
+            cfg.pool_probe_interval,
+            tx_stats.clone(),
             executor.clone(),
         );
 
@@ -125,32 +507,66 @@ impl Miner {
             request_handler,
             cpu_threads,
             cpu_worker_task_size: cfg.cpu_worker_task_size,
+            cpu_pipeline: cfg.cpu_pipeline,
             simd_extensions,
             numeric_id: cfg.numeric_id,
             start_nonce: cfg.start_nonce,
-            target_deadline: cfg.target_deadline,
             blocktime: cfg.blocktime,
             gpus: cfg.gpus,
             get_mining_info_interval: max(1000, cfg.get_mining_info_interval),
+            core_placement,
+            rpc_bind: cfg.rpc_bind,
+            status,
+            paused: Arc::new(AtomicBool::new(false)),
+            target_deadline: Arc::new(AtomicU64::new(cfg.target_deadline)),
+            stats_interval: max(1000, cfg.stats_interval),
+            rx_stats,
+            submission_max_retries: cfg.submission_max_retries,
+            shutdown: Arc::new(AtomicBool::new(false)),
+            push_enabled: cfg.push_enabled,
         }
     }
 
-    pub fn run(self) {
+    /// a clone-able handle to the request layer, so callers (e.g. a
+    /// Ctrl-C handler) can drain in-flight submissions on shutdown without
+    /// owning the `Miner` itself.
+    pub fn request_handler(&self) -> RequestHandler {
+        self.request_handler.clone()
+    }
+
+    pub fn run(self) -> MinerHandle {
         // create channels
         let (tx_rounds, rx_rounds) = unbounded();
         let (tx_nonce_data, rx_nonce_data) = mpsc::unbounded();
 
+        // expose live throughput/round status and pause/target_deadline
+        // controls over JSON-RPC, if configured.
+        rpc::start_server(
+            self.rpc_bind,
+            self.status.clone(),
+            self.paused.clone(),
+            self.target_deadline.clone(),
+        );
+
+        let status = self.status.clone();
+        let stats = Arc::new(Mutex::new(Statistics::default()));
+
         // create hasher thread
-        thread::spawn(create_scheduler_thread(
+        let scheduler_thread = thread::spawn(create_scheduler_thread(
             self.numeric_id,
             self.start_nonce,
             self.cpu_threads as u8,
             self.cpu_worker_task_size,
+            self.cpu_pipeline,
             self.simd_extensions.clone(),
             self.gpus,
             self.blocktime,
             rx_rounds.clone(),
             tx_nonce_data.clone(),
+            self.core_placement,
+            self.status,
+            self.paused,
+            self.shutdown.clone(),
         ));
 
         let state = Arc::new(Mutex::new(State::new()));
@@ -159,65 +575,51 @@ impl Miner {
         let inner_state = state.clone();
         let inner_tx_rounds = tx_rounds.clone();
         let get_mining_info_interval = self.get_mining_info_interval;
+        let executor = self.executor.clone();
         // run main mining loop on core
-        self.executor.clone().spawn(
-            Interval::new_interval(Duration::from_millis(get_mining_info_interval))
-                .for_each(move |_| {
-                    let state = inner_state.clone();
-                    let state2 = inner_state.clone();
-                    let state2 = state2.lock().unwrap();
-                    let capacity = state2.capacity;
-                    drop(state2);
-                    let tx_rounds = inner_tx_rounds.clone();
-                    request_handler.get_mining_info(capacity).then(move |mining_info| {
-                        match mining_info {
-                            Ok(mining_info) => {
-                                let mut state = state.lock().unwrap();
-                                state.first = false;
-                                if state.outage {
-                                    error!("{: <80}", "outage resolved.");
-                                    state.outage = false;
-                                }
-                                if mining_info.generation_signature != state.generation_signature {
-                                    state.update_mining_info(&mining_info);
-                                   
-                                    // communicate new round hasher
-                                    tx_rounds
-                                        .send(RoundInfo {
-                                            gensig: state.generation_signature_bytes,
-                                            base_target: state.base_target,
-                                            scoop: state.scoop.into(),
-                                            height: state.height,
-                                            block: state.block,
-                                        })
-                                        .expect("main thread can't communicate with hasher thread");
-                                }
-                            }
-                            _ => {
-                                let mut state = state.lock().unwrap();
-                                if state.first {
-                                    error!(
-                                        "{: <80}",
-                                        "error getting mining info, please check server config"
-                                    );
-                                    state.first = false;
-                                    state.outage = true;
-                                } else {
-                                    if !state.outage {
-                                        error!(
-                                            "{: <80}",
-                                            "error getting mining info => connection outage..."
-                                        );
-                                    }
-                                    state.outage = true;
-                                }
-                            }
-                        }
-                        future::ok(())
-                    })
-                })
-                .map_err(|e| panic!("interval errored: err={:?}", e)),
-        );
+        self.executor.clone().spawn(poll_mining_info(
+            executor,
+            request_handler,
+            inner_state,
+            inner_tx_rounds,
+            status.clone(),
+            get_mining_info_interval,
+            get_mining_info_interval,
+            self.shutdown.clone(),
+        ));
+
+        // push notifications (if enabled) feed new rounds through the same
+        // apply_new_mining_info path as the poll above, just without
+        // waiting for the next poll tick; the poll keeps running either way
+        // as a keep-alive/fallback.
+        if self.push_enabled {
+            let push_state = state.clone();
+            let push_tx_rounds = tx_rounds.clone();
+            self.request_handler.clone().start_push_listener(self.executor.clone(), move |mining_info| {
+                apply_new_mining_info(&push_state, &push_tx_rounds, mining_info);
+            });
+        }
+
+        // print a rolling scan-rate/deadline/submission summary independent
+        // of new-block/error logging, so long runs have some visibility
+        // even when nothing else is happening.
+        self.executor.clone().spawn(print_statistics(
+            self.executor.clone(),
+            stats.clone(),
+            status,
+            self.stats_interval,
+        ));
+
+        // re-send deadlines that never got confirmed, so a dropped response
+        // doesn't silently cost a won block on a lossy connection.
+        self.executor.clone().spawn(retry_pending_submissions(
+            self.executor.clone(),
+            self.rx_stats,
+            state.clone(),
+            stats.clone(),
+            self.request_handler.clone(),
+            self.submission_max_retries,
+        ));
 
         let target_deadline = self.target_deadline;
         let request_handler = self.request_handler.clone();
@@ -229,10 +631,16 @@ impl Miner {
                     state.capacity = nonce_data.capacity;
                     let deadline = nonce_data.deadline / nonce_data.base_target;
                     if state.block == nonce_data.block {
+                        {
+                            let mut stats = stats.lock().unwrap();
+                            stats.deadlines_found += 1;
+                            stats.best_deadline = min(stats.best_deadline, nonce_data.deadline_adjusted);
+                        }
                         if state.best_deadline > nonce_data.deadline_adjusted
-                            && nonce_data.deadline_adjusted < target_deadline
+                            && nonce_data.deadline_adjusted < target_deadline.load(Ordering::SeqCst)
                         {
                             state.best_deadline = nonce_data.deadline_adjusted;
+                            let gen_sig = state.generation_signature_bytes;
                             request_handler.submit_nonce(
                                 nonce_data.numeric_id,
                                 nonce_data.nonce,
@@ -240,7 +648,21 @@ impl Miner {
                                 nonce_data.block,
                                 nonce_data.deadline,
                                 deadline,
-                                state.generation_signature_bytes,
+                                gen_sig,
+                            );
+                            state.pending_submissions.insert(
+                                nonce_data.block,
+                                PendingSubmission {
+                                    numeric_id: nonce_data.numeric_id,
+                                    nonce: nonce_data.nonce,
+                                    height: nonce_data.height,
+                                    deadline_unadjusted: nonce_data.deadline,
+                                    deadline_adjusted: deadline,
+                                    gen_sig,
+                                    attempt: 0,
+                                    next_retry_at: Instant::now()
+                                        + Duration::from_millis(PENDING_SUBMISSION_BASE_BACKOFF_MS),
+                                },
                             );
                         }
                     }
@@ -248,5 +670,10 @@ impl Miner {
                 })
                 .map_err(|e| panic!("interval errored: err={:?}", e)),
         );
+
+        MinerHandle {
+            shutdown: self.shutdown,
+            scheduler_thread: Mutex::new(Some(scheduler_thread)),
+        }
     }
 }
@@ -1,5 +1,6 @@
 use self::core::{
-    ArgVal, ContextProperties, DeviceInfo, Event, KernelWorkGroupInfo, PlatformInfo, Status,
+    ArgVal, CommandQueueProperties, ContextProperties, DeviceInfo, DeviceType, Event,
+    KernelWorkGroupInfo, PlatformInfo, ProfilingInfo, Status,
 };
 use crate::gpu_hasher::GpuTask;
 use crate::poc_hashing::NONCE_SIZE;
@@ -34,6 +35,28 @@ pub struct GpuConfig {
     cores: usize,
 }
 
+/// nanosecond-accurate on-device execution time for one `gpu_hash` call,
+/// measured via `CL_PROFILING_COMMAND_START`/`CL_PROFILING_COMMAND_END` on
+/// the command queue's profiling events rather than host-side wall time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GpuKernelTimings {
+    pub noncegen_ns: u64,
+    pub calculate_deadlines_ns: u64,
+    pub find_min_ns: u64,
+}
+
+fn event_elapsed_ns(event: &Event) -> u64 {
+    let start = match core::get_event_profiling_info(event, ProfilingInfo::Start).unwrap() {
+        core::ProfilingInfoResult::Start(t) => t,
+        _ => panic!("Unexpected error. Can't obtain kernel start time."),
+    };
+    let end = match core::get_event_profiling_info(event, ProfilingInfo::End).unwrap() {
+        core::ProfilingInfoResult::End(t) => t,
+        _ => panic!("Unexpected error. Can't obtain kernel end time."),
+    };
+    end - start
+}
+
 //#[allow(dead_code)]
 pub struct GpuContext {
     queue: core::CommandQueue,
@@ -42,10 +65,13 @@ pub struct GpuContext {
     gdim0: [usize; 3],
     kernel1: core::Kernel,
     kernel2: core::Kernel,
-    buffer_gpu: core::Mem,
+    /// double-buffered so `noncegen` can run into one slot while
+    /// `calculate_deadlines`/`find_min`/readback are still draining the
+    /// other, instead of the host forcing a full `finish` between tasks.
+    buffer_gpu: [core::Mem; 2],
     gensig_gpu: core::Mem,
     pub worksize: usize,
-    deadlines_gpu: core::Mem,
+    deadlines_gpu: [core::Mem; 2],
     best_deadline_gpu: core::Mem,
     best_offset_gpu: core::Mem,
 }
@@ -72,9 +98,14 @@ impl GpuContext {
             None,
         )
         .unwrap();
-        let queue = core::create_command_queue(&context, &device_id, None).unwrap();
+        let queue = core::create_command_queue(
+            &context,
+            &device_id,
+            Some(CommandQueueProperties::new().profiling()),
+        )
+        .unwrap();
         let kernel0 = core::create_kernel(&program, "noncegen").unwrap();
-        let kernel_workgroup_size = get_kernel_work_group_size(&kernel0, device_id);
+        let kernel_workgroup_size = tuned_work_group_size(&kernel0, device_id);
         let workgroup_count = cores;
         let worksize = kernel_workgroup_size * workgroup_count;
         let gdim0 = [worksize, 1, 1];
@@ -82,8 +113,10 @@ impl GpuContext {
         let kernel1 = core::create_kernel(&program, "calculate_deadlines").unwrap();
         let kernel2 = core::create_kernel(&program, "find_min").unwrap();
 
-        // create buffers
-        let buffer_gpu = unsafe {
+        // create buffers; two slots each for buffer_gpu/deadlines_gpu so a
+        // noncegen dispatch can target the slot not currently being drained
+        // by the previous task's calculate_deadlines/find_min/readback.
+        let new_buffer_gpu = || unsafe {
             core::create_buffer::<_, u8>(
                 &context,
                 core::MEM_READ_WRITE,
@@ -92,21 +125,43 @@ impl GpuContext {
             )
             .expect("can't create gpu buffer")
         };
+        let buffer_gpu = [new_buffer_gpu(), new_buffer_gpu()];
 
+        // pinned (host-accessible) so the gensig upload and result readback
+        // can be issued asynchronously and overlap with kernel execution.
         let gensig_gpu = unsafe {
-            core::create_buffer::<_, u8>(&context, core::MEM_READ_ONLY, 32, None).unwrap()
+            core::create_buffer::<_, u8>(
+                &context,
+                core::MEM_READ_ONLY | core::MEM_ALLOC_HOST_PTR,
+                32,
+                None,
+            )
+            .unwrap()
         };
 
-        let deadlines_gpu = unsafe {
+        let new_deadlines_gpu = || unsafe {
             core::create_buffer::<_, u64>(&context, core::MEM_READ_WRITE, gdim0[0], None).unwrap()
         };
+        let deadlines_gpu = [new_deadlines_gpu(), new_deadlines_gpu()];
 
         let best_offset_gpu = unsafe {
-            core::create_buffer::<_, u64>(&context, core::MEM_READ_WRITE, 1, None).unwrap()
+            core::create_buffer::<_, u64>(
+                &context,
+                core::MEM_READ_WRITE | core::MEM_ALLOC_HOST_PTR,
+                1,
+                None,
+            )
+            .unwrap()
         };
 
         let best_deadline_gpu = unsafe {
-            core::create_buffer::<_, u64>(&context, core::MEM_READ_WRITE, 1, None).unwrap()
+            core::create_buffer::<_, u64>(
+                &context,
+                core::MEM_READ_WRITE | core::MEM_ALLOC_HOST_PTR,
+                1,
+                None,
+            )
+            .unwrap()
         };
 
         GpuContext {
@@ -159,7 +214,7 @@ pub fn platform_info() {
             .unwrap();
             let kernel = core::create_kernel(&program, "noncegen").unwrap();
             let cores = get_cores(*device_id) as usize;
-            let kernel_workgroup_size = get_kernel_work_group_size(&kernel, *device_id);
+            let kernel_workgroup_size = tuned_work_group_size(&kernel, *device_id);
             info!(
                 "OCL:     cores={},kernel_workgroupsize={}",
                 cores, kernel_workgroup_size
@@ -169,6 +224,44 @@ pub fn platform_info() {
     }
 }
 
+/// walks every OpenCL platform/device and returns a `GpuConfig` for every
+/// device matching `CL_DEVICE_TYPE_GPU`; pass `include_cpu_devices` to also
+/// pick up `CL_DEVICE_TYPE_CPU` devices (e.g. an OpenCL-capable CPU driver).
+/// `cores` is left at 0 so `gpu_get_info`/`gpu_init` fall back to the
+/// device's full compute-unit count, same as an explicit `cores: 0` in
+/// config.yaml.
+pub fn enumerate_gpus(include_cpu_devices: bool) -> Vec<GpuConfig> {
+    let mut gpus = Vec::new();
+    let platform_ids = core::get_platform_ids().unwrap();
+    for (platform_id, platform) in platform_ids.iter().enumerate() {
+        let device_ids = core::get_device_ids(&platform, None, None).unwrap();
+        for (device_id, device) in device_ids.iter().enumerate() {
+            let device_type = match core::get_device_info(device, DeviceInfo::Type).unwrap() {
+                core::DeviceInfoResult::Type(device_type) => device_type,
+                _ => panic!("Unexpected error. Can't obtain device type."),
+            };
+            let wanted = device_type.contains(DeviceType::GPU)
+                || (include_cpu_devices && device_type.contains(DeviceType::CPU));
+            if !wanted {
+                continue;
+            }
+            info!(
+                "gpu: auto-detected {} - {} [platform {}, device {}]",
+                to_string!(core::get_device_info(device, DeviceInfo::Vendor)),
+                to_string!(core::get_device_info(device, DeviceInfo::Name)),
+                platform_id,
+                device_id
+            );
+            gpus.push(GpuConfig {
+                platform_id,
+                device_id,
+                cores: 0,
+            });
+        }
+    }
+    gpus
+}
+
 fn get_cores(device: core::DeviceId) -> u32 {
     match core::get_device_info(device, DeviceInfo::MaxComputeUnits).unwrap() {
         core::DeviceInfoResult::MaxComputeUnits(mcu) => mcu,
@@ -218,7 +311,7 @@ pub fn gpu_get_info(gpus: &[GpuConfig], quiet: bool) -> u64 {
         )
         .unwrap();
         let kernel = core::create_kernel(&program, "noncegen").unwrap();
-        let kernel_workgroup_size = get_kernel_work_group_size(&kernel, device);
+        let kernel_workgroup_size = tuned_work_group_size(&kernel, device);
 
         let gpu_cores = if gpu.cores == 0 {
             max_compute_units as usize
@@ -303,13 +396,71 @@ fn get_kernel_work_group_size(x: &core::Kernel, y: core::DeviceId) -> usize {
     }
 }
 
-pub fn gpu_hash(gpu_context: &Arc<GpuContext>, task: &GpuTask) -> (u64, u64) {
+/// the SIMD width to round a kernel's local work-group size down to: the
+/// driver-reported `CL_KERNEL_PREFERRED_WORK_GROUP_SIZE_MULTIPLE` where
+/// available, else a vendor-based guess (NVIDIA warps are 32 lanes, AMD
+/// wavefronts are usually 64).
+fn warp_size(kernel: &core::Kernel, device: core::DeviceId) -> usize {
+    let preferred = core::get_kernel_work_group_info(
+        kernel,
+        device,
+        KernelWorkGroupInfo::PreferredWorkGroupSizeMultiple,
+    );
+    if let Ok(core::KernelWorkGroupInfoResult::PreferredWorkGroupSizeMultiple(warp)) = preferred {
+        if warp > 0 {
+            return warp;
+        }
+    }
+    let vendor = to_string!(core::get_device_info(&device, DeviceInfo::Vendor)).to_lowercase();
+    if vendor.contains("amd") || vendor.contains("advanced micro devices") {
+        64
+    } else {
+        32
+    }
+}
+
+/// `get_kernel_work_group_size`, rounded down to the largest multiple of
+/// `warp_size` that still fits, so occupancy isn't wasted on partial warps.
+fn tuned_work_group_size(kernel: &core::Kernel, device: core::DeviceId) -> usize {
+    let max_work_group_size = get_kernel_work_group_size(kernel, device);
+    let warp = warp_size(kernel, device);
+    if warp == 0 || max_work_group_size < warp {
+        return max_work_group_size;
+    }
+    (max_work_group_size / warp) * warp
+}
+
+/// a `noncegen` dispatch enqueued into one of `GpuContext`'s double-buffered
+/// slots but not yet waited on, so the caller can submit the *next* task's
+/// noncegen before finishing this one's pipeline with `gpu_finish` - the
+/// driver overlaps the two instead of the host blocking on `core::finish`
+/// between every stage.
+pub struct PendingNoncegen {
+    buffer_idx: usize,
+    /// one profiling event per noncegen sub-dispatch, for the aggregate
+    /// timing; the last one doubles as the completion dependency handed to
+    /// `calculate_deadlines`.
+    events: Vec<Event>,
+}
+
+/// enqueues `noncegen` for `task` into `buffer_idx` without waiting for it
+/// to complete. Pair with `gpu_finish` to run the rest of the pipeline.
+pub fn gpu_submit_noncegen(
+    gpu_context: &Arc<GpuContext>,
+    buffer_idx: usize,
+    task: &GpuTask,
+) -> PendingNoncegen {
     let numeric_id_be: u64 = task.numeric_id.to_be();
 
     let mut start;
     let mut end;
 
-    core::set_kernel_arg(&gpu_context.kernel0, 0, ArgVal::mem(&gpu_context.buffer_gpu)).unwrap();
+    core::set_kernel_arg(
+        &gpu_context.kernel0,
+        0,
+        ArgVal::mem(&gpu_context.buffer_gpu[buffer_idx]),
+    )
+    .unwrap();
     core::set_kernel_arg(
         &gpu_context.kernel0,
         1,
@@ -324,6 +475,9 @@ pub fn gpu_hash(gpu_context: &Arc<GpuContext>, task: &GpuTask) -> (u64, u64) {
     .unwrap();
     core::set_kernel_arg(&gpu_context.kernel0, 2, ArgVal::primitive(&numeric_id_be)).unwrap();
 
+    // one profiling event per noncegen dispatch, summed in gpu_finish for
+    // the total on-device noncegen time.
+    let mut events = Vec::with_capacity(8192 / GPU_HASHES_PER_RUN + 1);
     for i in (0..8192).step_by(GPU_HASHES_PER_RUN) {
         if i + GPU_HASHES_PER_RUN < 8192 {
             start = i;
@@ -336,6 +490,7 @@ pub fn gpu_hash(gpu_context: &Arc<GpuContext>, task: &GpuTask) -> (u64, u64) {
         core::set_kernel_arg(&gpu_context.kernel0, 3, ArgVal::primitive(&(start as i32))).unwrap();
         core::set_kernel_arg(&gpu_context.kernel0, 4, ArgVal::primitive(&(end as i32))).unwrap();
 
+        let mut event = Event::empty();
         unsafe {
             core::enqueue_kernel(
                 &gpu_context.queue,
@@ -345,14 +500,34 @@ pub fn gpu_hash(gpu_context: &Arc<GpuContext>, task: &GpuTask) -> (u64, u64) {
                 &gpu_context.gdim0,
                 Some(gpu_context.ldim0),
                 None::<Event>,
-                None::<&mut Event>,
+                Some(&mut event),
             )
             .unwrap();
         }
+        events.push(event);
     }
-    core::finish(&gpu_context.queue).unwrap();
 
-    upload_gensig(&gpu_context, task.round.gensig, true);
+    PendingNoncegen { buffer_idx, events }
+}
+
+/// waits on `pending`'s noncegen completion, then runs the rest of the
+/// pipeline (gensig upload, `calculate_deadlines`, `find_min`, readback)
+/// against its buffer slot. Call one task "behind" `gpu_submit_noncegen` so
+/// the next task's noncegen is already queued by the time this blocks.
+pub fn gpu_finish(
+    gpu_context: &Arc<GpuContext>,
+    pending: PendingNoncegen,
+    task: &GpuTask,
+) -> (u64, u64, GpuKernelTimings) {
+    let PendingNoncegen { buffer_idx, events } = pending;
+    let noncegen_completion = events.last().expect("noncegen always dispatches at least once");
+    core::wait_for_event(noncegen_completion).unwrap();
+    let noncegen_ns: u64 = events.iter().map(event_elapsed_ns).sum();
+
+    // noncegen has already completed (waited on above); the write is
+    // enqueued without blocking the host, ahead of calculate_deadlines on
+    // the same queue.
+    upload_gensig(&gpu_context, task.round.gensig, false);
 
     // calc deadline
 
@@ -362,11 +537,16 @@ pub fn gpu_hash(gpu_context: &Arc<GpuContext>, task: &GpuTask) -> (u64, u64) {
         ArgVal::mem(&gpu_context.gensig_gpu),
     )
     .unwrap();
-    core::set_kernel_arg(&gpu_context.kernel1, 1, ArgVal::mem(&gpu_context.buffer_gpu)).unwrap();
+    core::set_kernel_arg(
+        &gpu_context.kernel1,
+        1,
+        ArgVal::mem(&gpu_context.buffer_gpu[buffer_idx]),
+    )
+    .unwrap();
     core::set_kernel_arg(
         &gpu_context.kernel1,
         2,
-        ArgVal::mem(&gpu_context.deadlines_gpu),
+        ArgVal::mem(&gpu_context.deadlines_gpu[buffer_idx]),
     )
     .unwrap();
 
@@ -377,6 +557,7 @@ pub fn gpu_hash(gpu_context: &Arc<GpuContext>, task: &GpuTask) -> (u64, u64) {
     )
     .unwrap();
 
+    let mut calculate_deadlines_event = Event::empty();
     unsafe {
         core::enqueue_kernel(
             &gpu_context.queue,
@@ -385,8 +566,8 @@ pub fn gpu_hash(gpu_context: &Arc<GpuContext>, task: &GpuTask) -> (u64, u64) {
             None,
             &gpu_context.gdim0,
             Some(gpu_context.ldim0),
-            None::<Event>,
-            None::<&mut Event>,
+            Some(noncegen_completion.clone()),
+            Some(&mut calculate_deadlines_event),
         )
         .unwrap();
     }
@@ -394,7 +575,7 @@ pub fn gpu_hash(gpu_context: &Arc<GpuContext>, task: &GpuTask) -> (u64, u64) {
     core::set_kernel_arg(
         &gpu_context.kernel2,
         0,
-        ArgVal::mem(&gpu_context.deadlines_gpu),
+        ArgVal::mem(&gpu_context.deadlines_gpu[buffer_idx]),
     )
     .unwrap();
     core::set_kernel_arg(
@@ -422,6 +603,7 @@ pub fn gpu_hash(gpu_context: &Arc<GpuContext>, task: &GpuTask) -> (u64, u64) {
     )
     .unwrap();
 
+    let mut find_min_event = Event::empty();
     unsafe {
         core::enqueue_kernel(
             &gpu_context.queue,
@@ -430,14 +612,28 @@ pub fn gpu_hash(gpu_context: &Arc<GpuContext>, task: &GpuTask) -> (u64, u64) {
             None,
             &gpu_context.gdim0,
             Some(gpu_context.ldim0),
-            None::<Event>,
-            None::<&mut Event>,
+            Some(calculate_deadlines_event.clone()),
+            Some(&mut find_min_event),
         )
         .unwrap();
     }
-    
-    get_result(&gpu_context)
 
+    core::wait_for_event(&find_min_event).unwrap();
+    let timings = GpuKernelTimings {
+        noncegen_ns,
+        calculate_deadlines_ns: event_elapsed_ns(&calculate_deadlines_event),
+        find_min_ns: event_elapsed_ns(&find_min_event),
+    };
+
+    let (best_deadline, best_offset) = get_result(&gpu_context);
+    (best_deadline, best_offset, timings)
+}
+
+/// non-pipelined single-shot hash for callers (e.g. `--benchmark`) that
+/// don't have a "next" task to overlap with.
+pub fn gpu_hash(gpu_context: &Arc<GpuContext>, task: &GpuTask) -> (u64, u64, GpuKernelTimings) {
+    let pending = gpu_submit_noncegen(gpu_context, 0, task);
+    gpu_finish(gpu_context, pending, task)
 }
 
 pub fn get_result(gpu_context: &Arc<GpuContext>) -> (u64, u64) {
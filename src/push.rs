@@ -0,0 +1,130 @@
+use crate::com::api::{parse_json_result, FetchError, MiningInfoResponse as MiningInfo};
+use crate::com::client::Client;
+use futures::future::{self, Future};
+use futures::stream::Stream;
+use futures::{try_ready, Async, Poll};
+use reqwest::r#async::Chunk;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::runtime::TaskExecutor;
+use tokio::timer::Delay;
+
+/// how long to wait before reconnecting a push connection that failed to
+/// open, or dropped mid-stream.
+const RECONNECT_BACKOFF_MS: u64 = 5_000;
+
+/// splits a raw HTTP chunk stream into newline-delimited JSON notifications.
+/// HTTP/TCP chunking has no relation to application-message boundaries - a
+/// notification can arrive split across two chunks, or two can coalesce
+/// into one - so this buffers bytes across chunks and only decodes once a
+/// full line has arrived, instead of assuming chunk == message. A trailing
+/// line with no terminator (the connection closed mid-message) is decoded
+/// as-is rather than silently dropped.
+struct NdjsonStream<S> {
+    inner: S,
+    buf: Vec<u8>,
+}
+
+impl<S> NdjsonStream<S> {
+    fn new(inner: S) -> Self {
+        Self { inner, buf: Vec::new() }
+    }
+}
+
+impl<S> Stream for NdjsonStream<S>
+where
+    S: Stream<Item = Chunk, Error = FetchError>,
+{
+    type Item = MiningInfo;
+    type Error = FetchError;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            if let Some(pos) = self.buf.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = self.buf.drain(..=pos).collect();
+                let line = &line[..line.len() - 1];
+                if line.iter().all(|b| b.is_ascii_whitespace()) {
+                    // blank keep-alive line, nothing to decode
+                    continue;
+                }
+                return Ok(Async::Ready(Some(parse_json_result(line)?)));
+            }
+            match try_ready!(self.inner.poll()) {
+                Some(chunk) => self.buf.extend_from_slice(&chunk),
+                None if self.buf.iter().any(|b| !b.is_ascii_whitespace()) => {
+                    let line = std::mem::replace(&mut self.buf, Vec::new());
+                    return Ok(Async::Ready(Some(parse_json_result(&line)?)));
+                }
+                None => return Ok(Async::Ready(None)),
+            }
+        }
+    }
+}
+
+/// a stratum-style alternative to polling `get_mining_info` on an interval
+/// (see `miner::poll_mining_info`): holds a long-lived connection to the
+/// pool's push endpoint and invokes `on_notify` with each job notification
+/// the moment it arrives, instead of waiting up to the poll interval to
+/// find out about new work. The poll keeps running alongside this as a
+/// keep-alive/fallback, so a push connection that's silently stuck doesn't
+/// stall new-round detection entirely. Reconnects with a fixed backoff on
+/// any connection error or stream drop.
+pub fn start_push_listener<F>(client: Client, executor: TaskExecutor, on_notify: F)
+where
+    F: Fn(MiningInfo) + Send + Sync + 'static,
+{
+    let on_notify: Arc<dyn Fn(MiningInfo) + Send + Sync> = Arc::new(on_notify);
+    executor.clone().spawn(listen(client, executor, on_notify));
+}
+
+fn listen(
+    client: Client,
+    executor: TaskExecutor,
+    on_notify: Arc<dyn Fn(MiningInfo) + Send + Sync>,
+) -> Box<dyn Future<Item = (), Error = ()> + Send> {
+    let stream_notify = on_notify.clone();
+    let reconnect_client = client.clone();
+    let reconnect_executor = executor.clone();
+    Box::new(
+        client
+            .open_mining_info_stream()
+            .then(move |res| -> Box<dyn Future<Item = (), Error = ()> + Send> {
+                match res {
+                    Ok(stream) => Box::new(
+                        NdjsonStream::new(stream)
+                            .for_each(move |mining_info| {
+                                stream_notify(mining_info);
+                                Ok(())
+                            })
+                            .then(move |res| {
+                                if let Err(e) = res {
+                                    warn!("push: connection lost: {:?}", e);
+                                }
+                                schedule_reconnect(reconnect_executor, reconnect_client, on_notify);
+                                future::ok(())
+                            }),
+                    ),
+                    Err(e) => {
+                        warn!("push: failed to connect: {:?}", e);
+                        schedule_reconnect(reconnect_executor, reconnect_client, on_notify);
+                        Box::new(future::ok(()))
+                    }
+                }
+            }),
+    )
+}
+
+fn schedule_reconnect(
+    executor: TaskExecutor,
+    client: Client,
+    on_notify: Arc<dyn Fn(MiningInfo) + Send + Sync>,
+) {
+    executor.clone().spawn(
+        Delay::new(Instant::now() + Duration::from_millis(RECONNECT_BACKOFF_MS))
+            .map_err(|e| error!("push reconnect timer errored: {:?}", e))
+            .and_then(move |_| {
+                executor.spawn(listen(client, executor.clone(), on_notify));
+                future::ok(())
+            }),
+    );
+}
@@ -1,10 +1,12 @@
-use crate::com::api::{FetchError, MiningInfoResponse};
+use crate::com::api::{FetchError, MiningInfoResponse, TransientError};
 use crate::com::client::{Client, ProxyDetails, SubmissionParameters};
 use crate::future::prio_retry::PrioRetry;
+use crossbeam_channel::Sender as StatSender;
 use futures::future::Future;
 use futures::stream::Stream;
 use futures::sync::mpsc;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 use std::u64;
 use tokio;
@@ -12,20 +14,42 @@ use tokio::runtime::TaskExecutor;
 use url::Url;
 use stopwatch::Stopwatch;
 use std::sync::Arc;
+use std::thread;
+
+/// how long to sleep between checks while draining in-flight submissions
+/// on shutdown.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// a submission outcome, for the rolling stats summary and the
+/// pending-submission retry tracker in `miner::run`. Sent alongside the
+/// existing per-submission log lines, not in place of them - `Transient`
+/// isn't reported since `handle_submissions` already resends it
+/// internally, so it's not yet a final outcome.
+#[derive(Clone, Copy, Debug)]
+pub enum StatEvent {
+    Confirmed { block: u64, nonce: u64 },
+    Rejected { block: u64, nonce: u64 },
+}
 
 #[derive(Clone)]
 pub struct RequestHandler {
     client: Client,
     tx_submit_data: mpsc::UnboundedSender<SubmissionParameters>,
+    /// number of nonce submissions currently awaiting a response, so
+    /// shutdown can wait for them to finish instead of dropping them.
+    in_flight: Arc<AtomicUsize>,
 }
 
 impl RequestHandler {
     pub fn new(
-        base_uri: Url,
+        urls: Vec<Url>,
         secret_phrase: String,
-        timeout: u64,
+        connect_timeout: u64,
+        request_timeout: u64,
         send_proxy_details: bool,
         additional_headers: Arc<HashMap<String, String>>,
+        pool_probe_interval: u64,
+        tx_stats: StatSender<StatEvent>,
         executor: TaskExecutor,
     ) -> RequestHandler {
         // TODO
@@ -36,24 +60,30 @@ impl RequestHandler {
         };
 
         let client = Client::new(
-            base_uri,
+            urls,
             secret_phrase,
-            timeout,
+            connect_timeout,
+            request_timeout,
             proxy_details,
             additional_headers,
         );
+        client.start_health_probe(executor.clone(), pool_probe_interval);
 
         let (tx_submit_data, rx_submit_nonce_data) = mpsc::unbounded();
+        let in_flight = Arc::new(AtomicUsize::new(0));
         RequestHandler::handle_submissions(
             client.clone(),
             rx_submit_nonce_data,
             tx_submit_data.clone(),
+            in_flight.clone(),
+            tx_stats,
             executor,
         );
 
         RequestHandler {
             client,
             tx_submit_data,
+            in_flight,
         }
     }
 
@@ -61,11 +91,16 @@ impl RequestHandler {
         client: Client,
         rx: mpsc::UnboundedReceiver<SubmissionParameters>,
         tx_submit_data: mpsc::UnboundedSender<SubmissionParameters>,
+        in_flight: Arc<AtomicUsize>,
+        tx_stats: StatSender<StatEvent>,
         executor: TaskExecutor,
     ) {
         let stream = PrioRetry::new(rx, Duration::from_secs(3))
             .and_then(move |submission_params| {
                 let tx_submit_data = tx_submit_data.clone();
+                let in_flight = in_flight.clone();
+                let tx_stats = tx_stats.clone();
+                in_flight.fetch_add(1, Ordering::SeqCst);
                 let mut sw = Stopwatch::new();
                 sw.start();
                 client
@@ -73,8 +108,13 @@ impl RequestHandler {
                     .submit_nonce(&submission_params)
                     .then(move |res| {
                         sw.stop();
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
                         match res {
                             Ok(res) => {
+                                let _ = tx_stats.send(StatEvent::Confirmed {
+                                    block: submission_params.block,
+                                    nonce: submission_params.nonce,
+                                });
                                 if submission_params.deadline != res.deadline {
                                     log_deadline_mismatch(
                                         submission_params.height,
@@ -94,46 +134,39 @@ impl RequestHandler {
                                     );
                                 }
                             }
-                            Err(FetchError::Pool(e)) => {
-                                // Very intuitive, if some pools send an empty message they are
-                                // experiencing too much load expect the submission to be resent later.
-                                if e.message.is_empty() || e.message == "limit exceeded" {
-                                    log_pool_busy(
-                                        submission_params.height,
-                                        submission_params.account_id,
-                                        submission_params.nonce,
-                                        submission_params.deadline,
-                                        sw.elapsed_ms()
-                                    );
-                                    let res = tx_submit_data.unbounded_send(submission_params);
-                                    if let Err(e) = res {
-                                        error!("can't send submission params: {}", e);
-                                    }
-                                } else {
-                                    log_submission_not_accepted(
-                                        submission_params.height,
-                                        submission_params.account_id,
-                                        submission_params.nonce,
-                                        submission_params.deadline,
-                                        sw.elapsed_ms(),
-                                        e.code,
-                                        &e.message,
-                                    );
-                                }
-                            }
-                            Err(FetchError::Http(x)) => {
-                                log_submission_failed(
+                            Err(FetchError::Transient(e)) => {
+                                // worth another shot: network blip, overloaded
+                                // pool or a reported "busy" - resend it.
+                                log_submission_transient_failure(
                                     submission_params.height,
                                     submission_params.account_id,
                                     submission_params.nonce,
                                     submission_params.deadline,
-                                    &x.to_string(),
+                                    sw.elapsed_ms(),
+                                    &e,
                                 );
                                 let res = tx_submit_data.unbounded_send(submission_params);
                                 if let Err(e) = res {
                                     error!("can't send submission params: {}", e);
                                 }
                             }
+                            Err(FetchError::Permanent(e)) => {
+                                // the pool understood and rejected it for a
+                                // protocol reason, resending won't help.
+                                let _ = tx_stats.send(StatEvent::Rejected {
+                                    block: submission_params.block,
+                                    nonce: submission_params.nonce,
+                                });
+                                log_submission_rejected(
+                                    submission_params.height,
+                                    submission_params.account_id,
+                                    submission_params.nonce,
+                                    submission_params.deadline,
+                                    sw.elapsed_ms(),
+                                    e.code,
+                                    &e.message,
+                                );
+                            }
                         };
                         Ok(())
                     })
@@ -147,6 +180,33 @@ impl RequestHandler {
         self.client.get_mining_info(capacity, additional_headers)
     }
 
+    /// starts a long-lived push connection (see `push::start_push_listener`)
+    /// as an alternative to polling `get_mining_info` on an interval;
+    /// `on_notify` is invoked with each freshly-decoded `MiningInfoResponse`
+    /// the moment the pool pushes it.
+    pub fn start_push_listener<F>(&self, executor: TaskExecutor, on_notify: F)
+    where
+        F: Fn(MiningInfoResponse) + Send + Sync + 'static,
+    {
+        crate::push::start_push_listener(self.client.clone(), executor, on_notify);
+    }
+
+    /// the pool/proxy endpoint currently in use, so callers can surface it
+    /// in logs or the status snapshot.
+    pub fn current_endpoint(&self) -> Url {
+        self.client.current_endpoint()
+    }
+
+    /// every configured endpoint with its last observed health, so the
+    /// status snapshot can show an automatic failover as it happens. This
+    /// surfaces `Client`'s own failover/failback bookkeeping (one priority
+    /// list, one active index, shared across submissions and polling) -
+    /// there's no separate per-`RequestHandler` health-check policy on top
+    /// of it.
+    pub fn endpoint_health(&self) -> Vec<(Url, bool)> {
+        self.client.endpoint_health()
+    }
+
     pub fn submit_nonce(
         &self,
         account_id: u64,
@@ -170,6 +230,26 @@ impl RequestHandler {
             error!("can't send submission params: {}", e);
         }
     }
+
+    /// blocks until every in-flight nonce submission has completed (or has
+    /// been resubmitted and completed), or until `timeout_ms` has elapsed,
+    /// whichever comes first. Used on graceful shutdown so submissions
+    /// aren't dropped mid-flight.
+    pub fn drain(&self, timeout_ms: u64) {
+        let deadline = Stopwatch::start_new();
+        while self.in_flight.load(Ordering::SeqCst) > 0 && (deadline.elapsed_ms() as u64) < timeout_ms {
+            thread::sleep(DRAIN_POLL_INTERVAL);
+        }
+        let remaining = self.in_flight.load(Ordering::SeqCst);
+        if remaining > 0 {
+            warn!(
+                "shutdown: {} submission(s) still in flight after {}ms, exiting anyway",
+                remaining, timeout_ms
+            );
+        } else {
+            info!("shutdown: all submissions drained");
+        }
+    }
 }
 
 fn log_deadline_mismatch(
@@ -187,17 +267,24 @@ fn log_deadline_mismatch(
     );
 }
 
-fn log_submission_failed(height: u64, account_id: u64, nonce: u64, deadline: u64, err: &str) {
+fn log_submission_transient_failure(
+    height: u64,
+    account_id: u64,
+    nonce: u64,
+    deadline: u64,
+    latency: i64,
+    err: &TransientError,
+) {
     warn!(
         "{: <80}",
         format!(
-            "submission failed, retrying: height={}, id={}, nonce={}, dl={}, response={}",
-            height, account_id, nonce, deadline, err
+            "submission failed, retrying: height={}, id={}, nonce={}, dl={}, latency={}ms, reason={:?}",
+            height, account_id, nonce, deadline, latency, err
         )
     );
 }
 
-fn log_submission_not_accepted(
+fn log_submission_rejected(
     height: u64,
     account_id: u64,
     nonce: u64,
@@ -206,8 +293,8 @@ fn log_submission_not_accepted(
     err_code: i32,
     msg: &str,
 ) {
-    error!(
-        "dl rejected: height={}, id={}, nonce={}, \
+    warn!(
+        "dl rejected, not retrying: height={}, id={}, nonce={}, \
          dl={}, latency={}ms\n\tcode: {}\n\tmessage: {}",
         height, account_id, nonce, deadline, latency, err_code, msg,
     );
@@ -220,13 +307,6 @@ fn log_submission_accepted(height: u64, account_id: u64, nonce: u64, deadline: u
     );
 }
 
-fn log_pool_busy(height: u64, account_id: u64, nonce: u64, deadline: u64, latency: i64) {
-    info!(
-        "pool busy, retrying: height={}, id={}, nonce={}, dl={}, latency={}ms",
-        height, account_id, nonce, deadline, latency
-    );
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
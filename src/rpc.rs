@@ -0,0 +1,127 @@
+// A small JSON-RPC 2.0 server (one request per line, newline-delimited)
+// exposing a live scheduler status snapshot and a couple of runtime
+// controls, so a dashboard can poll/steer a long-running benchmark
+// without editing config.yaml and restarting.
+use crate::scheduler::SchedulerStatus;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// starts the RPC listener in its own thread if `bind` is non-empty; a
+/// no-op otherwise so the feature stays opt-in.
+pub fn start_server(
+    bind: String,
+    status: Arc<Mutex<SchedulerStatus>>,
+    paused: Arc<AtomicBool>,
+    target_deadline: Arc<AtomicU64>,
+) {
+    if bind.is_empty() {
+        return;
+    }
+
+    let listener = match TcpListener::bind(&bind) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("rpc: can't bind {}: {}", bind, e);
+            return;
+        }
+    };
+    info!("rpc: listening on {}", bind);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let status = status.clone();
+                    let paused = paused.clone();
+                    let target_deadline = target_deadline.clone();
+                    thread::spawn(move || handle_client(stream, status, paused, target_deadline));
+                }
+                Err(e) => error!("rpc: accept failed: {}", e),
+            }
+        }
+    });
+}
+
+fn handle_client(
+    stream: TcpStream,
+    status: Arc<Mutex<SchedulerStatus>>,
+    paused: Arc<AtomicBool>,
+    target_deadline: Arc<AtomicU64>,
+) {
+    let reader = match stream.try_clone() {
+        Ok(s) => BufReader::new(s),
+        Err(e) => {
+            error!("rpc: can't clone connection: {}", e);
+            return;
+        }
+    };
+    let mut writer = stream;
+
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = handle_request(&line, &status, &paused, &target_deadline);
+        if writeln!(writer, "{}", response).is_err() {
+            break;
+        }
+    }
+}
+
+fn handle_request(
+    line: &str,
+    status: &Arc<Mutex<SchedulerStatus>>,
+    paused: &Arc<AtomicBool>,
+    target_deadline: &Arc<AtomicU64>,
+) -> String {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return rpc_error(Value::Null, -32700, &format!("parse error: {}", e)),
+    };
+
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = request.get("method").and_then(Value::as_str).unwrap_or("");
+
+    match method {
+        "getStatus" => rpc_result(id, status.lock().unwrap().to_json()),
+        "pause" => {
+            paused.store(true, Ordering::SeqCst);
+            rpc_result(id, json!({ "paused": true }))
+        }
+        "resume" => {
+            paused.store(false, Ordering::SeqCst);
+            rpc_result(id, json!({ "paused": false }))
+        }
+        "setTargetDeadline" => {
+            let value = request
+                .get("params")
+                .and_then(|params| params.get("target_deadline"))
+                .and_then(Value::as_u64);
+            match value {
+                Some(value) => {
+                    target_deadline.store(value, Ordering::SeqCst);
+                    rpc_result(id, json!({ "target_deadline": value }))
+                }
+                None => rpc_error(id, -32602, "missing u64 param `target_deadline`"),
+            }
+        }
+        _ => rpc_error(id, -32601, &format!("method not found: {}", method)),
+    }
+}
+
+fn rpc_result(id: Value, result: Value) -> String {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string()
+}
+
+fn rpc_error(id: Value, code: i32, message: &str) -> String {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }).to_string()
+}
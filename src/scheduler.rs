@@ -1,4 +1,4 @@
-use crate::cpu_hasher::{hash_cpu, CpuTask, SimdExtension};
+use crate::cpu_hasher::{hash_cpu, hash_cpu_pipelined, CpuTask, SimdExtension};
 #[cfg(feature = "opencl")]
 use crate::gpu_hasher::{create_gpu_hasher_thread, GpuTask};
 use crate::miner::NonceData;
@@ -8,12 +8,21 @@ use crate::ocl::GpuConfig;
 use chrono::Local;
 use crossbeam_channel::{unbounded, Receiver};
 use futures::sync::mpsc::UnboundedSender;
-use std::cmp::min;
-#[cfg(feature = "opencl")]
+use serde_json::json;
+use std::cmp::{max, min};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::u64;
 use stopwatch::Stopwatch;
 
+// how long a chunk should keep a device busy, used to size the next chunk
+// from its own measured throughput.
+const TARGET_MS_PER_CHUNK: u64 = 100;
+const MIN_CHUNK_SIZE: u64 = 16;
+const MAX_CHUNK_SIZE: u64 = 1 << 20;
+
 #[derive(Clone)]
 pub struct RoundInfo {
     pub gensig: [u8; 32],
@@ -22,11 +31,160 @@ pub struct RoundInfo {
     pub height: u64,
 }
 
+/// Identifies a hashing device for throughput tracking, independent of the
+/// channel it communicates on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DeviceId {
+    Cpu(usize),
+    Gpu(usize),
+}
+
+impl DeviceId {
+    fn rpc_name(&self) -> String {
+        match self {
+            DeviceId::Cpu(i) => format!("cpu{}", i),
+            DeviceId::Gpu(i) => format!("gpu{}", i),
+        }
+    }
+}
+
+/// per-device throughput snapshot, as reported to the RPC subsystem.
+#[derive(Clone, Default)]
+pub struct DeviceStatus {
+    pub nonces_per_sec: f64,
+    pub total_nonces: u64,
+}
+
+/// live snapshot of scheduler state, updated by the scheduler thread and
+/// polled by the RPC subsystem from its own thread, hence the mutex.
+#[derive(Default)]
+pub struct SchedulerStatus {
+    pub devices: HashMap<String, DeviceStatus>,
+    pub height: u64,
+    pub block: u64,
+    pub scoop: u64,
+    pub best_deadline: u64,
+    /// the pool/proxy endpoint currently in use, so operators can see
+    /// which one is live after a failover.
+    pub active_endpoint: String,
+    /// every configured endpoint, in priority order, with its last observed
+    /// health - so an automatic failover/failback shows up here as it
+    /// happens, not just as the new `active_endpoint`. Sourced from
+    /// `Client`'s own failover (one priority list, one active index) via
+    /// `RequestHandler::endpoint_health` - there is no separate per-miner
+    /// health-check mechanism layered on top.
+    pub endpoints: Vec<(String, bool)>,
+}
+
+impl SchedulerStatus {
+    pub fn to_json(&self) -> serde_json::Value {
+        let xpus: serde_json::Map<String, serde_json::Value> = self
+            .devices
+            .iter()
+            .map(|(name, d)| {
+                (
+                    name.clone(),
+                    json!({
+                        "nonces_per_sec": d.nonces_per_sec,
+                        "total_nonces": d.total_nonces,
+                    }),
+                )
+            })
+            .collect();
+        let endpoints: Vec<serde_json::Value> = self
+            .endpoints
+            .iter()
+            .map(|(url, healthy)| json!({ "url": url, "healthy": healthy }))
+            .collect();
+        json!({
+            "xpus": xpus,
+            "round": { "height": self.height, "block": self.block, "scoop": self.scoop },
+            "best_deadline": self.best_deadline,
+            "active_endpoint": self.active_endpoint,
+            "endpoints": endpoints,
+        })
+    }
+}
+
 pub enum HasherMessage {
-    CpuRequestForWork,
+    CpuRequestForWork(usize),
     GpuRequestForWork(usize),
     NoncesProcessed(u64),
+    /// a device finished a chunk: used to update its EWMA throughput.
+    WorkReport {
+        device: DeviceId,
+        nonces: u64,
+        elapsed_ms: u64,
+    },
     SubmitDeadline((u64, u64, u64)), //(height, nonce, deadline)
+    /// nanosecond-accurate per-kernel timings for one GPU chunk, from
+    /// `CL_PROFILING_COMMAND_START`/`_END` events rather than wall time.
+    GpuKernelTimings {
+        device: DeviceId,
+        noncegen_ns: u64,
+        calculate_deadlines_ns: u64,
+        find_min_ns: u64,
+    },
+}
+
+/// exponentially-weighted moving average of nonces/ms per device, used to
+/// size the next chunk handed to that device so every worker finishes a
+/// round's work at roughly the same cadence.
+struct ThroughputTracker {
+    rates: HashMap<DeviceId, f64>,
+}
+
+impl ThroughputTracker {
+    fn new() -> Self {
+        Self {
+            rates: HashMap::new(),
+        }
+    }
+
+    fn report(&mut self, device: DeviceId, nonces: u64, elapsed_ms: u64) {
+        let rate = nonces as f64 / max(1, elapsed_ms) as f64;
+        let entry = self.rates.entry(device).or_insert(rate);
+        *entry = 0.25 * rate + 0.75 * *entry;
+    }
+
+    /// the current EWMA throughput estimate for `device`, in nonces/ms,
+    /// or 0.0 if it hasn't reported yet.
+    fn rate(&self, device: DeviceId) -> f64 {
+        *self.rates.get(&device).unwrap_or(&0.0)
+    }
+
+    /// chunk size targeting `TARGET_MS_PER_CHUNK` of this device's recent
+    /// throughput, clamped to the configured bounds, to `buffer_capacity`
+    /// (e.g. a GPU's fixed-size `buffer_gpu`, which a rate-driven target
+    /// must never exceed), and to what remains. Rounded down to a multiple
+    /// of `lanes` (the device's SIMD width, 1 for GPUs and scalar CPU) so a
+    /// packed `noncegen_*` call never generates a partial lane past the
+    /// `local_nonces` nonces the caller's buffer was sized for.
+    fn next_chunk_size(
+        &self,
+        device: DeviceId,
+        fallback: u64,
+        remaining: u64,
+        buffer_capacity: u64,
+        lanes: u64,
+    ) -> u64 {
+        let target = match self.rates.get(&device) {
+            Some(rate) => (*rate * TARGET_MS_PER_CHUNK as f64) as u64,
+            None => fallback,
+        };
+        let size = min(
+            remaining,
+            min(buffer_capacity, max(MIN_CHUNK_SIZE, min(MAX_CHUNK_SIZE, target))),
+        );
+        (size / lanes) * lanes
+    }
+}
+
+/// reserves the next `size` nonces from a shared cursor, returning the
+/// start of the reserved range. Devices pull from the same global range
+/// regardless of how unevenly they are sized.
+fn reserve_nonces(cursor: &AtomicU64, size: u64) -> u64 {
+    cursor.fetch_add(size, Ordering::SeqCst)
 }
 
 pub fn create_scheduler_thread(
@@ -34,11 +192,16 @@ pub fn create_scheduler_thread(
     start_nonce: u64,
     cpu_threads: u8,
     cpu_task_size: u64,
+    cpu_pipeline: bool,
     simd_ext: SimdExtension,
     gpus: Vec<GpuConfig>,
     blocktime: u64,
     rx_rounds: Receiver<RoundInfo>,
     tx_nonce: UnboundedSender<NonceData>,
+    core_placement: Option<Vec<(usize, u64)>>,
+    status: Arc<Mutex<SchedulerStatus>>,
+    paused: Arc<AtomicBool>,
+    shutdown: Arc<AtomicBool>,
 ) -> impl FnOnce() {
     move || {
         let thread_pool = rayon::ThreadPoolBuilder::new()
@@ -48,6 +211,40 @@ pub fn create_scheduler_thread(
 
         let (tx, rx) = unbounded();
 
+        // with affinity enabled, a worker's initial chunk size (before it has
+        // an EWMA rate of its own) is weighted by its core's capacity
+        // relative to the fastest core, so efficiency cores don't get handed
+        // the same chunk as performance cores on the first round.
+        let max_capacity = core_placement
+            .as_ref()
+            .and_then(|p| p.iter().map(|(_, capacity)| *capacity).max())
+            .unwrap_or(1);
+        let cpu_fallback = |worker_id: usize| match &core_placement {
+            Some(p) => max(
+                MIN_CHUNK_SIZE,
+                (cpu_task_size as f64 * p[worker_id].1 as f64 / max_capacity as f64) as u64,
+            ),
+            None => cpu_task_size,
+        };
+
+        // when pipelining is enabled each cpu worker is a long-lived thread
+        // (like the gpu workers below) so it can keep its in-flight buffer
+        // across tasks instead of starting from scratch every chunk.
+        let mut cpu_threads_handles = Vec::new();
+        let mut cpu_channels = Vec::new();
+        if cpu_pipeline {
+            for worker_id in 0..cpu_threads as usize {
+                cpu_channels.push(unbounded());
+                let core_id = core_placement.as_ref().map(|p| p[worker_id].0);
+                cpu_threads_handles.push(thread::spawn(hash_cpu_pipelined(
+                    tx.clone(),
+                    cpu_channels.last().unwrap().1.clone(),
+                    simd_ext.clone(),
+                    core_id,
+                )));
+            }
+        }
+
         // create gpu threads and channels
         #[cfg(feature = "opencl")]
         let gpu_contexts = if gpus.len() > 0 {
@@ -80,49 +277,71 @@ pub fn create_scheduler_thread(
         }
 
         let mut sw = Stopwatch::start_new();
+        let mut throughput = ThroughputTracker::new();
 
-        for round in &rx_rounds {
+        'rounds: for round in &rx_rounds {
             sw.restart();
             let nonces_to_hash = u64::MAX - start_nonce;
-            let mut requested = 0u64;
+            let cursor = Arc::new(AtomicU64::new(0));
             let mut processed = 0u64;
 
-            // kickoff first gpu and cpu runs
+            {
+                let mut status = status.lock().unwrap();
+                status.height = round.height;
+                status.block = round.block;
+                status.scoop = round.scoop;
+                status.best_deadline = u64::MAX;
+            }
+
+            // kickoff first gpu and cpu runs, each device getting its own
+            // share of the shared nonce range sized to its last measured rate.
             #[cfg(feature = "opencl")]
             for (i, gpu) in gpus.iter().enumerate() {
-                // schedule next gpu task
-                let task_size = min(gpu.worksize as u64, nonces_to_hash - requested);
-                if task_size > 0 {
+                let device = DeviceId::Gpu(i);
+                let remaining = nonces_to_hash - cursor.load(Ordering::SeqCst);
+                let task_size =
+                    throughput.next_chunk_size(device, gpu.worksize as u64, remaining, gpu.worksize as u64, 1);
+                if task_size > 0 && !paused.load(Ordering::SeqCst) {
+                    let local_startnonce = start_nonce + reserve_nonces(&cursor, task_size);
                     gpu_channels[i]
                         .0
                         .send(Some(GpuTask {
                             numeric_id,
-                            local_startnonce: start_nonce + requested,
+                            local_startnonce,
                             local_nonces: task_size,
                             round: round.clone(),
                         }))
                         .unwrap();
                 }
-                requested += task_size;
             }
 
             // kickoff first cpu runs
-            for _ in 0..cpu_threads {
-                let task_size = min(cpu_task_size, nonces_to_hash - requested);
-                if task_size > 0 {
-                    let task = hash_cpu(
-                        tx.clone(),
-                        CpuTask {
-                            numeric_id,
-                            local_startnonce: start_nonce + requested,
-                            local_nonces: task_size,
-                            round: round.clone(),
-                        },
-                        simd_ext.clone(),
-                    );
-                    thread_pool.spawn(task);
+            for worker_id in 0..cpu_threads as usize {
+                let device = DeviceId::Cpu(worker_id);
+                let remaining = nonces_to_hash - cursor.load(Ordering::SeqCst);
+                let task_size = throughput.next_chunk_size(
+                    device,
+                    cpu_fallback(worker_id),
+                    remaining,
+                    MAX_CHUNK_SIZE,
+                    simd_ext.lanes(),
+                );
+                if task_size > 0 && !paused.load(Ordering::SeqCst) {
+                    let local_startnonce = start_nonce + reserve_nonces(&cursor, task_size);
+                    let task = CpuTask {
+                        worker_id,
+                        numeric_id,
+                        local_startnonce,
+                        local_nonces: task_size,
+                        round: round.clone(),
+                        core_id: core_placement.as_ref().map(|p| p[worker_id].0),
+                    };
+                    if cpu_pipeline {
+                        cpu_channels[worker_id].0.send(Some(task)).unwrap();
+                    } else {
+                        thread_pool.spawn(hash_cpu(tx.clone(), task, simd_ext.clone()));
+                    }
                 }
-                requested += task_size;
             }
 
             // control loop
@@ -130,48 +349,102 @@ pub fn create_scheduler_thread(
             for msg in rx {
                 match msg {
                     // schedule next cpu task
-                    HasherMessage::CpuRequestForWork => {
-                        let task_size = min(cpu_task_size, nonces_to_hash - requested);
-                        if task_size > 0 {
-                            let task = hash_cpu(
-                                tx.clone(),
-                                CpuTask {
-                                    numeric_id: numeric_id,
-                                    local_startnonce: start_nonce + requested,
-                                    local_nonces: task_size,
-                                    round: round.clone(),
-                                },
-                                simd_ext.clone(),
-                            );
-                            thread_pool.spawn(task);
+                    HasherMessage::CpuRequestForWork(worker_id) => {
+                        let device = DeviceId::Cpu(worker_id);
+                        let remaining = nonces_to_hash - cursor.load(Ordering::SeqCst);
+                        let task_size = throughput.next_chunk_size(
+                            device,
+                            cpu_fallback(worker_id),
+                            remaining,
+                            MAX_CHUNK_SIZE,
+                            simd_ext.lanes(),
+                        );
+                        if task_size > 0 && !paused.load(Ordering::SeqCst) {
+                            let local_startnonce = start_nonce + reserve_nonces(&cursor, task_size);
+                            let task = CpuTask {
+                                worker_id,
+                                numeric_id,
+                                local_startnonce,
+                                local_nonces: task_size,
+                                round: round.clone(),
+                                core_id: core_placement.as_ref().map(|p| p[worker_id].0),
+                            };
+                            if cpu_pipeline {
+                                cpu_channels[worker_id].0.send(Some(task)).unwrap();
+                            } else {
+                                thread_pool.spawn(hash_cpu(tx.clone(), task, simd_ext.clone()));
+                            }
                         }
-                        requested += task_size;
                         print_status(processed, &sw, blocktime)
                     }
                     // schedule next gpu task
                     HasherMessage::GpuRequestForWork(id) => {
+                        let device = DeviceId::Gpu(id);
+                        let remaining = nonces_to_hash - cursor.load(Ordering::SeqCst);
                         #[cfg(feature = "opencl")]
-                        let task_size = min(gpus[id].worksize as u64, nonces_to_hash - requested);
+                        let task_size = throughput.next_chunk_size(
+                            device,
+                            gpus[id].worksize as u64,
+                            remaining,
+                            gpus[id].worksize as u64,
+                            1,
+                        );
                         #[cfg(not(feature = "opencl"))]
                         let task_size = 0;
                         #[cfg(feature = "opencl")]
-                        gpu_channels[id]
-                            .0
-                            .send(Some(GpuTask {
-                                numeric_id: numeric_id,
-                                local_startnonce: start_nonce + requested,
-                                local_nonces: task_size,
-                                round: round.clone(),
-                            }))
-                            .unwrap();
-                        requested += task_size;
+                        {
+                            if !paused.load(Ordering::SeqCst) {
+                                let local_startnonce = start_nonce + reserve_nonces(&cursor, task_size);
+                                gpu_channels[id]
+                                    .0
+                                    .send(Some(GpuTask {
+                                        numeric_id,
+                                        local_startnonce,
+                                        local_nonces: task_size,
+                                        round: round.clone(),
+                                    }))
+                                    .unwrap();
+                            }
+                        }
                         print_status(processed, &sw, blocktime)
                     }
                     HasherMessage::NoncesProcessed(nonces) => {
                         processed += nonces;
                     }
+                    HasherMessage::WorkReport {
+                        device,
+                        nonces,
+                        elapsed_ms,
+                    } => {
+                        throughput.report(device, nonces, elapsed_ms);
+                        let mut status = status.lock().unwrap();
+                        let entry = status.devices.entry(device.rpc_name()).or_default();
+                        entry.nonces_per_sec = throughput.rate(device) * 1000.0;
+                        entry.total_nonces += nonces;
+                    }
+                    HasherMessage::GpuKernelTimings {
+                        device,
+                        noncegen_ns,
+                        calculate_deadlines_ns,
+                        find_min_ns,
+                    } => {
+                        info!(
+                            "{}: noncegen={:.2}ms, calculate_deadlines={:.2}ms, find_min={:.2}ms",
+                            device.rpc_name(),
+                            noncegen_ns as f64 / 1_000_000.0,
+                            calculate_deadlines_ns as f64 / 1_000_000.0,
+                            find_min_ns as f64 / 1_000_000.0,
+                        );
+                    }
                     HasherMessage::SubmitDeadline((height, nonce, deadline)) => {
+                        {
+                            let mut status = status.lock().unwrap();
+                            if deadline < status.best_deadline {
+                                status.best_deadline = deadline;
+                            }
+                        }
                         // calc capcaity
+                        let requested = cursor.load(Ordering::SeqCst);
                         let capacity = requested * 250 * blocktime / 1024 / (1 + sw.elapsed_ms()) as u64;
                         tx_nonce
                             .clone()
@@ -186,11 +459,37 @@ pub fn create_scheduler_thread(
                             .expect("failed to send nonce data");
                     }
                 }
+                // abandon the current round promptly rather than finishing
+                // the whole scan: a fresher round is already queued, or a
+                // shutdown was requested and there won't be another.
+                if shutdown.load(Ordering::SeqCst) {
+                    break 'rounds;
+                }
                 if rx_rounds.len() > 0 {
                     break;
                 }
             }
         }
+
+        // stop the long-lived pipelined cpu/gpu worker threads and wait for
+        // them to exit. `hash_cpu` tasks already queued on the rayon pool
+        // (non-pipelined mode) are not waited for; dropping the pool does
+        // not block.
+        for (tx, _) in &cpu_channels {
+            let _ = tx.send(None);
+        }
+        for handle in cpu_threads_handles {
+            let _ = handle.join();
+        }
+        #[cfg(feature = "opencl")]
+        {
+            for (tx, _) in &gpu_channels {
+                let _ = tx.send(None);
+            }
+            for handle in gpu_threads {
+                let _ = handle.join();
+            }
+        }
     }
 }
 